@@ -2,6 +2,8 @@
 
 pub mod prelude;
 
+pub mod artifactory;
+pub mod artifactory_runtime;
 pub mod doc_module_versions;
 pub mod doc_modules;
 pub mod doc_versions;
@@ -45,6 +47,8 @@ pub mod st_yunxiao_task_events;
 pub mod st_yunxiao_task_events_history;
 
 seaography::register_entity_modules_read_only!([
+    artifactory,
+    artifactory_runtime,
     doc_module_versions,
     doc_modules,
     doc_versions,
@@ -1,5 +1,7 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.14
 
+pub use super::artifactory::Entity as Artifactory;
+pub use super::artifactory_runtime::Entity as ArtifactoryRuntime;
 pub use super::doc_module_versions::Entity as DocModuleVersions;
 pub use super::doc_modules::Entity as DocModules;
 pub use super::doc_versions::Entity as DocVersions;
@@ -44,6 +44,9 @@ pub struct Model {
     pub created_avatar_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub deleted_at: Option<DateTimeWithTimeZone>,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub success_rule: Option<Json>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,43 @@
+-- graphql 订阅(/gql/ws)依赖的状态变更通知
+-- 通道名取数据库参数 gql.notify_channel(未设置时为 gql_state_change), 需与 GQL_NOTIFY_CHANNEL 一致:
+--   ALTER DATABASE <db> SET gql.notify_channel = '<channel>';
+-- 数据库参数只对新建的连接生效, 修改后需重启写入方的连接池
+
+CREATE OR REPLACE FUNCTION gql_notify_state_change() RETURNS trigger AS $$
+DECLARE
+    state_col text := TG_ARGV[0];
+    new_state text := to_jsonb(NEW) ->> state_col;
+    channel text := coalesce(nullif(current_setting('gql.notify_channel', true), ''), 'gql_state_change');
+    old_state text;
+BEGIN
+    IF TG_OP = 'UPDATE' THEN
+        old_state := to_jsonb(OLD) ->> state_col;
+        IF new_state IS NOT DISTINCT FROM old_state THEN
+            RETURN NEW;
+        END IF;
+    END IF;
+    PERFORM pg_notify(channel, json_build_object(
+        'table', TG_TABLE_NAME,
+        'id', NEW.id,
+        'state', new_state,
+        'prevState', old_state,
+        'at', now()
+    )::text);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS gql_state_notify ON st_workflow;
+CREATE TRIGGER gql_state_notify
+    AFTER INSERT OR UPDATE OF state ON st_workflow
+    FOR EACH ROW EXECUTE FUNCTION gql_notify_state_change('state');
+
+DROP TRIGGER IF EXISTS gql_state_notify ON st_wf_sol_pack;
+CREATE TRIGGER gql_state_notify
+    AFTER INSERT OR UPDATE OF state ON st_wf_sol_pack
+    FOR EACH ROW EXECUTE FUNCTION gql_notify_state_change('state');
+
+DROP TRIGGER IF EXISTS gql_state_notify ON st_polling_task;
+CREATE TRIGGER gql_state_notify
+    AFTER INSERT OR UPDATE OF status ON st_polling_task
+    FOR EACH ROW EXECUTE FUNCTION gql_notify_state_change('status');
@@ -0,0 +1,2 @@
+-- 轮询任务完成条件, 结构见 src/polling/rule.rs SuccessRule
+ALTER TABLE st_polling_task ADD COLUMN IF NOT EXISTS success_rule jsonb;
@@ -0,0 +1,26 @@
+-- searchSolutions 全文检索索引
+-- 表达式需与 src/services/graphql/custom_query/solution_search.rs 中一致,
+-- 使用非 simple 的 GQL_SEARCH_TS_CONFIG 时将 'simple' 替换为对应 configuration 重建
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX IF NOT EXISTS idx_solution_search_tsv ON solution USING gin (
+    (
+        setweight(to_tsvector('simple', coalesce(solution_desc_abbreviated, '')), 'A')
+        || setweight(to_tsvector('simple', coalesce(solution_desc_text, '')), 'B')
+        || setweight(to_tsvector('simple', coalesce(notes, '')), 'D')
+    )
+);
+CREATE INDEX IF NOT EXISTS idx_solution_way_search_tsv ON solution_way USING gin (
+    (setweight(to_tsvector('simple', coalesce(background, '')), 'C'))
+);
+
+-- 中文未分词时的子串匹配兜底(concat_ws 非 immutable, 不能用于索引表达式)
+CREATE INDEX IF NOT EXISTS idx_solution_search_trgm ON solution USING gin (
+    (
+        coalesce(solution_desc_abbreviated, '') || ' ' || coalesce(solution_desc_text, '')
+        || ' ' || coalesce(notes, '')
+    ) gin_trgm_ops
+);
+CREATE INDEX IF NOT EXISTS idx_solution_way_search_trgm ON solution_way USING gin (
+    (coalesce(background, '')) gin_trgm_ops
+);
@@ -0,0 +1,253 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::middleware::auth::AuthClaims;
+pub mod service;
+
+/// 审批方式(`approval_type`), 兼容 any_of / anyOf / ANY_OF 等写法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalType {
+    // 任一角色通过即通过
+    AnyOf,
+    // 全部角色通过才通过
+    AllOf,
+    // 按 list_approval_role 顺序逐个通过
+    Sequential,
+}
+
+impl ApprovalType {
+    pub fn parse(v: &str) -> Option<Self> {
+        match v.replace(['_', '-'], "").to_lowercase().as_str() {
+            "anyof" => Some(ApprovalType::AnyOf),
+            "allof" => Some(ApprovalType::AllOf),
+            "sequential" => Some(ApprovalType::Sequential),
+            _ => None,
+        }
+    }
+}
+
+/// `list_approval_role` 中的一项: `"admin"` 或 `{"role": "admin", "users": ["u1"]}`
+///
+/// 配置 `users` 时仅限这些用户(jwt sub)审批, 否则持有该角色即可
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ApprovalRole {
+    Name(String),
+    Detail {
+        role: String,
+        #[serde(default)]
+        users: Vec<String>,
+    },
+}
+
+impl ApprovalRole {
+    pub fn role(&self) -> &str {
+        match self {
+            ApprovalRole::Name(role) | ApprovalRole::Detail { role, .. } => role,
+        }
+    }
+
+    pub fn accepts(&self, claims: &AuthClaims) -> bool {
+        match self {
+            ApprovalRole::Detail { users, .. } if !users.is_empty() => claims
+                .sub
+                .as_ref()
+                .is_some_and(|sub| users.iter().any(|u| u == sub)),
+            _ => claims.roles.iter().any(|r| r == self.role()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    Approve,
+    Reject,
+}
+
+/// `approval_result` 中的一条审批记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalRecord {
+    pub role: String,
+    pub approver: Option<String>,
+    pub decision: Decision,
+    pub comment: Option<String>,
+    pub at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// 审批完成后的回调(`approval_callback`), 为空对象时不回调
+///
+/// ```json
+/// {"url": "http://svr/approval/done", "method": "POST", "headers": {"X-Token": "t"}, "maxRetry": 3}
+/// ```
+///
+/// `maxRetry` 最大为 10; 回调只在内存中重试, 不保证送达, 见 `service::spawn_callback`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalCallback {
+    pub url: String,
+    pub method: Option<String>,
+    #[serde(default)]
+    pub headers: serde_json::Map<String, Value>,
+    pub max_retry: Option<u32>,
+}
+
+impl ApprovalCallback {
+    pub fn parse(v: &Value) -> Option<Self> {
+        serde_json::from_value(v.clone()).ok()
+    }
+}
+
+/// 解析后的审批流
+#[derive(Debug, Clone)]
+pub struct ApprovalFlow {
+    pub kind: ApprovalType,
+    pub roles: Vec<ApprovalRole>,
+    pub records: Vec<ApprovalRecord>,
+}
+
+impl ApprovalFlow {
+    ///
+    /// 已通过的角色(按 list_approval_role 顺序)
+    fn approved(&self, role: &str) -> bool {
+        self.records
+            .iter()
+            .any(|r| r.role == role && r.decision == Decision::Approve)
+    }
+
+    ///
+    /// 尚待审批的角色; 顺序审批时只有第一个待审批角色可操作
+    pub fn pending_roles(&self) -> Vec<&ApprovalRole> {
+        let pending = self.roles.iter().filter(|r| !self.approved(r.role()));
+        match self.kind {
+            ApprovalType::Sequential => pending.take(1).collect(),
+            _ => pending.collect(),
+        }
+    }
+
+    ///
+    /// 审批结论: 任一驳回即驳回, 否则按审批方式判断是否通过
+    pub fn outcome(&self) -> Outcome {
+        if self.records.iter().any(|r| r.decision == Decision::Reject) {
+            return Outcome::Rejected;
+        }
+        let passed = match self.kind {
+            ApprovalType::AnyOf => self.roles.iter().any(|r| self.approved(r.role())),
+            ApprovalType::AllOf | ApprovalType::Sequential => {
+                self.roles.iter().all(|r| self.approved(r.role()))
+            }
+        };
+        if passed {
+            Outcome::Approved
+        } else {
+            Outcome::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(role: &str, decision: Decision) -> ApprovalRecord {
+        ApprovalRecord {
+            role: role.to_owned(),
+            approver: None,
+            decision,
+            comment: None,
+            at: String::new(),
+        }
+    }
+
+    fn flow(kind: ApprovalType, records: Vec<ApprovalRecord>) -> ApprovalFlow {
+        ApprovalFlow {
+            kind,
+            roles: vec![
+                ApprovalRole::Name("a".to_owned()),
+                ApprovalRole::Name("b".to_owned()),
+            ],
+            records,
+        }
+    }
+
+    fn roles(flow: &ApprovalFlow) -> Vec<&str> {
+        flow.pending_roles().into_iter().map(|r| r.role()).collect()
+    }
+
+    #[test]
+    fn any_of() {
+        let f = flow(ApprovalType::AnyOf, vec![]);
+        assert_eq!(f.outcome(), Outcome::Pending);
+        assert_eq!(roles(&f), vec!["a", "b"]);
+        let f = flow(ApprovalType::AnyOf, vec![record("b", Decision::Approve)]);
+        assert_eq!(f.outcome(), Outcome::Approved);
+    }
+
+    #[test]
+    fn all_of() {
+        let f = flow(ApprovalType::AllOf, vec![record("b", Decision::Approve)]);
+        assert_eq!(f.outcome(), Outcome::Pending);
+        assert_eq!(roles(&f), vec!["a"]);
+        let f = flow(
+            ApprovalType::AllOf,
+            vec![
+                record("b", Decision::Approve),
+                record("a", Decision::Approve),
+            ],
+        );
+        assert_eq!(f.outcome(), Outcome::Approved);
+        assert!(roles(&f).is_empty());
+    }
+
+    #[test]
+    fn sequential_exposes_one_role_at_a_time() {
+        let f = flow(ApprovalType::Sequential, vec![]);
+        assert_eq!(roles(&f), vec!["a"]);
+        let f = flow(
+            ApprovalType::Sequential,
+            vec![record("a", Decision::Approve)],
+        );
+        assert_eq!(roles(&f), vec!["b"]);
+        assert_eq!(f.outcome(), Outcome::Pending);
+    }
+
+    #[test]
+    fn any_reject_rejects() {
+        let f = flow(
+            ApprovalType::AnyOf,
+            vec![
+                record("a", Decision::Approve),
+                record("b", Decision::Reject),
+            ],
+        );
+        assert_eq!(f.outcome(), Outcome::Rejected);
+    }
+
+    #[test]
+    fn records_of_unknown_roles_are_ignored() {
+        let f = flow(ApprovalType::AnyOf, vec![record("c", Decision::Approve)]);
+        assert_eq!(f.outcome(), Outcome::Pending);
+    }
+
+    #[test]
+    fn parse_approval_type() {
+        assert_eq!(ApprovalType::parse("ANY_OF"), Some(ApprovalType::AnyOf));
+        assert_eq!(ApprovalType::parse("allOf"), Some(ApprovalType::AllOf));
+        assert_eq!(ApprovalType::parse("all-of"), Some(ApprovalType::AllOf));
+        assert_eq!(
+            ApprovalType::parse("Sequential"),
+            Some(ApprovalType::Sequential)
+        );
+        assert_eq!(ApprovalType::parse("majority"), None);
+    }
+}
@@ -0,0 +1,327 @@
+use actix_web::http::Method;
+use chrono::Local;
+use entity_graphql::{fc_cfg_approval_flow, st_wf_approval_flow};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DatabaseTransaction, EntityTrait, QueryFilter, QuerySelect,
+    TransactionTrait,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+
+use super::{
+    ApprovalCallback, ApprovalFlow, ApprovalRecord, ApprovalRole, ApprovalType, Decision, Outcome,
+};
+use crate::error::{DError, LogicErr};
+use crate::middleware::auth::AuthClaims;
+
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(10);
+const CALLBACK_MAX_RETRY: u32 = 3;
+// maxRetry 取自数据, 限制上限
+const CALLBACK_MAX_RETRY_LIMIT: u32 = 10;
+const CALLBACK_BACKOFF_BASE: Duration = Duration::from_secs(2);
+const CALLBACK_BACKOFF_MAX: Duration = Duration::from_secs(300);
+
+/// 审批流所在的表, 两张表的审批字段结构一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalTable {
+    // st_wf_approval_flow
+    Workflow,
+    // fc_cfg_approval_flow
+    FeatureConfig,
+}
+
+impl ApprovalTable {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "st_wf_approval_flow" => Some(ApprovalTable::Workflow),
+            "fc_cfg_approval_flow" => Some(ApprovalTable::FeatureConfig),
+            _ => None,
+        }
+    }
+
+    pub fn table(self) -> &'static str {
+        match self {
+            ApprovalTable::Workflow => "st_wf_approval_flow",
+            ApprovalTable::FeatureConfig => "fc_cfg_approval_flow",
+        }
+    }
+}
+
+/// 审批操作后的审批流状态
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalState {
+    pub flow: &'static str,
+    pub id: i32,
+    pub approval_type: ApprovalType,
+    pub outcome: Outcome,
+    pub is_complete: bool,
+    pub records: Vec<ApprovalRecord>,
+    pub pending_roles: Vec<String>,
+}
+
+/// 两张审批表的公共字段
+struct FlowRow {
+    roles: Value,
+    result: Value,
+    kind: String,
+    callback: Value,
+    is_complete: bool,
+    is_delete: bool,
+}
+
+///
+/// 以当前身份审批(通过/驳回)一个审批流: 记录结果, 得出结论后置 is_complete 并触发回调
+pub async fn decide(
+    db: &DatabaseConnection,
+    table: ApprovalTable,
+    id: i32,
+    claims: &AuthClaims,
+    decision: Decision,
+    comment: Option<String>,
+) -> Result<ApprovalState, DError> {
+    let txn = db.begin().await?;
+    let row = load(&txn, table, id)
+        .await?
+        .filter(|r| !r.is_delete)
+        .ok_or_else(|| DError::Custom(LogicErr::NotFound(format!("{} <{}>", table.table(), id))))?;
+    if row.is_complete {
+        return Err(DError::Custom(LogicErr::IllegalTransition(format!(
+            "{} <{}> already complete",
+            table.table(),
+            id
+        ))));
+    }
+    let mut flow = parse_flow(&row)?;
+    let role = flow
+        .pending_roles()
+        .into_iter()
+        .find(|r| r.accepts(claims))
+        .map(|r| r.role().to_owned())
+        .ok_or_else(|| {
+            DError::Custom(LogicErr::Forbidden(format!(
+                "not a pending approver of {} <{}>",
+                table.table(),
+                id
+            )))
+        })?;
+    flow.records.push(ApprovalRecord {
+        role,
+        approver: claims.sub.clone(),
+        decision,
+        comment,
+        at: Local::now().to_rfc3339(),
+    });
+    let outcome = flow.outcome();
+    let is_complete = outcome != Outcome::Pending;
+    save(
+        &txn,
+        table,
+        id,
+        serde_json::to_value(&flow.records)?,
+        is_complete,
+    )
+    .await?;
+    txn.commit().await?;
+
+    let state = ApprovalState {
+        flow: table.table(),
+        id,
+        approval_type: flow.kind,
+        outcome,
+        is_complete,
+        pending_roles: flow
+            .pending_roles()
+            .into_iter()
+            .map(|r| r.role().to_owned())
+            .collect(),
+        records: flow.records,
+    };
+    if is_complete {
+        if let Some(callback) = ApprovalCallback::parse(&row.callback) {
+            spawn_callback(callback, serde_json::to_value(&state)?);
+        }
+    }
+    Ok(state)
+}
+
+fn parse_flow(row: &FlowRow) -> Result<ApprovalFlow, DError> {
+    let params_err = |msg: String| DError::Custom(LogicErr::ParamsError(msg));
+    let kind = ApprovalType::parse(&row.kind)
+        .ok_or_else(|| params_err(format!("approval_type <{}>", row.kind)))?;
+    let roles: Vec<ApprovalRole> = serde_json::from_value(row.roles.clone())
+        .map_err(|e| params_err(format!("list_approval_role: {}", e)))?;
+    if roles.is_empty() {
+        return Err(params_err("list_approval_role is empty".to_owned()));
+    }
+    // 历史数据可能为 {} 等非数组结构, 视为无审批记录
+    let records = match &row.result {
+        Value::Array(_) => serde_json::from_value(row.result.clone())
+            .map_err(|e| params_err(format!("approval_result: {}", e)))?,
+        _ => vec![],
+    };
+    Ok(ApprovalFlow {
+        kind,
+        roles,
+        records,
+    })
+}
+
+async fn load(
+    txn: &DatabaseTransaction,
+    table: ApprovalTable,
+    id: i32,
+) -> Result<Option<FlowRow>, DError> {
+    let row = match table {
+        ApprovalTable::Workflow => st_wf_approval_flow::Entity::find_by_id(id)
+            .lock_exclusive()
+            .one(txn)
+            .await?
+            .map(|m| FlowRow {
+                roles: m.list_approval_role,
+                result: m.approval_result,
+                kind: m.approval_type,
+                callback: m.approval_callback,
+                is_complete: m.is_complete,
+                is_delete: m.is_delete,
+            }),
+        ApprovalTable::FeatureConfig => fc_cfg_approval_flow::Entity::find_by_id(id)
+            .lock_exclusive()
+            .one(txn)
+            .await?
+            .map(|m| FlowRow {
+                roles: m.list_approval_role,
+                result: m.approval_result,
+                kind: m.approval_type,
+                callback: m.approval_callback,
+                is_complete: m.is_complete,
+                is_delete: m.is_delete,
+            }),
+    };
+    Ok(row)
+}
+
+async fn save(
+    txn: &DatabaseTransaction,
+    table: ApprovalTable,
+    id: i32,
+    result: Value,
+    is_complete: bool,
+) -> Result<(), DError> {
+    match table {
+        ApprovalTable::Workflow => {
+            st_wf_approval_flow::Entity::update_many()
+                .col_expr(
+                    st_wf_approval_flow::Column::ApprovalResult,
+                    Expr::value(result),
+                )
+                .col_expr(
+                    st_wf_approval_flow::Column::IsComplete,
+                    Expr::value(is_complete),
+                )
+                .filter(st_wf_approval_flow::Column::Id.eq(id))
+                .exec(txn)
+                .await?;
+        }
+        ApprovalTable::FeatureConfig => {
+            fc_cfg_approval_flow::Entity::update_many()
+                .col_expr(
+                    fc_cfg_approval_flow::Column::ApprovalResult,
+                    Expr::value(result),
+                )
+                .col_expr(
+                    fc_cfg_approval_flow::Column::IsComplete,
+                    Expr::value(is_complete),
+                )
+                .filter(fc_cfg_approval_flow::Column::Id.eq(id))
+                .exec(txn)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+///
+/// 第 `retry` 次重试前的等待时间: base * 2^(n-1), 不超过 max
+fn callback_backoff(retry: u32) -> Duration {
+    let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+    CALLBACK_BACKOFF_BASE
+        .saturating_mul(factor)
+        .min(CALLBACK_BACKOFF_MAX)
+}
+
+///
+/// 异步触发审批完成回调, 非 2xx 或请求失败时按指数退避重试
+///
+/// 回调不落库(fire-and-forget): 进程重启时未完成的回调会丢失, 放弃时记录 error 日志(含 payload),
+/// 需要可靠投递时由接收方按 is_complete 对账
+fn spawn_callback(callback: ApprovalCallback, payload: Value) {
+    actix_web::rt::spawn(async move {
+        let client = awc::Client::default();
+        let method = callback.method.as_deref().unwrap_or("POST").to_uppercase();
+        let Ok(method) = Method::from_bytes(method.as_bytes()) else {
+            tracing::error!("[approval] invalid callback method <{}>", method);
+            return;
+        };
+        let max_retry = callback
+            .max_retry
+            .unwrap_or(CALLBACK_MAX_RETRY)
+            .min(CALLBACK_MAX_RETRY_LIMIT);
+        for retry in 0..=max_retry {
+            if retry > 0 {
+                actix_web::rt::time::sleep(callback_backoff(retry)).await;
+            }
+            let mut req = client
+                .request(method.clone(), callback.url.as_str())
+                .timeout(CALLBACK_TIMEOUT);
+            for (k, v) in &callback.headers {
+                let v = match v {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                req = req.insert_header((k.as_str(), v));
+            }
+            match req.send_json(&payload).await {
+                Ok(resp) if resp.status().is_success() => {
+                    tracing::info!("[approval] callback <{}> done", callback.url);
+                    return;
+                }
+                Ok(resp) => tracing::warn!(
+                    "[approval] callback <{}> attempt {} got {}",
+                    callback.url,
+                    retry,
+                    resp.status()
+                ),
+                Err(e) => tracing::warn!(
+                    "[approval] callback <{}> attempt {} failed: {}",
+                    callback.url,
+                    retry,
+                    e
+                ),
+            }
+        }
+        tracing::error!(
+            "[approval] callback <{}> gave up after {} retries, payload: {}",
+            callback.url,
+            max_retry,
+            payload
+        );
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn callback_backoff_is_capped() {
+        assert_eq!(callback_backoff(1), CALLBACK_BACKOFF_BASE);
+        assert_eq!(callback_backoff(3), CALLBACK_BACKOFF_BASE * 4);
+        assert_eq!(callback_backoff(10), CALLBACK_BACKOFF_MAX);
+        // 不溢出
+        assert_eq!(callback_backoff(u32::MAX), CALLBACK_BACKOFF_MAX);
+        assert_eq!(callback_backoff(0), CALLBACK_BACKOFF_BASE);
+    }
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::source::EnvOverride;
+
+/// graphql 接口鉴权配置(bearer jwt)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthSetting {
+    // 未显式配置时, 设置了密钥或jwks即开启
+    pub enable: bool,
+    // HS256/384/512 共享密钥
+    pub jwt_secret: Option<String>,
+    // RS/ES/EdDSA 公钥集合(jwks json文件)
+    pub jwks_file: Option<PathBuf>,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    // 角色所在的 claim, 支持字符串数组或空格分隔字符串
+    pub role_claim: String,
+    // 按角色限制实体/字段访问的策略文件
+    pub policy_file: Option<PathBuf>,
+    // 免鉴权路径; /metrics 默认需要鉴权, 供 prometheus 匿名抓取时需显式加入
+    pub public_paths: Vec<String>,
+    // 可调用管理接口(如清空查询缓存)的角色
+    pub admin_roles: Vec<String>,
+}
+
+impl Default for AuthSetting {
+    fn default() -> Self {
+        AuthSetting {
+            enable: false,
+            jwt_secret: None,
+            jwks_file: None,
+            issuer: None,
+            audience: None,
+            role_claim: "roles".to_owned(),
+            policy_file: None,
+            public_paths: vec!["/gql/health".to_owned()],
+            admin_roles: vec!["admin".to_owned()],
+        }
+    }
+}
+
+impl AuthSetting {
+    ///
+    /// `explicit_enable`: 配置文件中是否显式设置了 enable
+    pub(super) fn override_env(&mut self, env: &mut EnvOverride, explicit_enable: bool) {
+        env.opt("AUTH_JWT_SECRET", &mut self.jwt_secret);
+        env.opt("AUTH_JWKS_FILE", &mut self.jwks_file);
+        env.opt("AUTH_JWT_ISSUER", &mut self.issuer);
+        env.opt("AUTH_JWT_AUDIENCE", &mut self.audience);
+        env.parse("AUTH_ROLE_CLAIM", &mut self.role_claim);
+        env.opt("AUTH_POLICY_FILE", &mut self.policy_file);
+        env.list("AUTH_PUBLIC_PATHS", &mut self.public_paths);
+        env.list("AUTH_ADMIN_ROLES", &mut self.admin_roles);
+        self.jwt_secret = self.jwt_secret.take().filter(|v| !v.is_empty());
+        if !explicit_enable && env.var("AUTH_ENABLE").is_none() {
+            self.enable = self.jwt_secret.is_some() || self.jwks_file.is_some();
+        }
+        env.parse("AUTH_ENABLE", &mut self.enable);
+    }
+}
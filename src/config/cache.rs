@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::source::EnvOverride;
+
+/// graphql 查询结果缓存配置(redis)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheSetting {
+    pub enable: bool,
+    // 默认 TTL(秒), 0 表示不缓存
+    pub default_ttl: u64,
+    // 按实体(表名)单独设置的 TTL(秒)
+    pub entity_ttl: HashMap<String, u64>,
+}
+
+impl Default for CacheSetting {
+    fn default() -> Self {
+        CacheSetting {
+            enable: true,
+            default_ttl: 0,
+            entity_ttl: HashMap::new(),
+        }
+    }
+}
+
+impl CacheSetting {
+    pub(super) fn override_env(&mut self, env: &mut EnvOverride) {
+        env.parse("GQL_CACHE_ENABLE", &mut self.enable);
+        env.parse("GQL_CACHE_TTL", &mut self.default_ttl);
+        // 格式: feature_config:5,solution:10
+        let Some(v) = env.var("GQL_CACHE_ENTITY_TTL") else {
+            return;
+        };
+        let mut entity_ttl = HashMap::new();
+        for kv in v.split(',').map(str::trim).filter(|kv| !kv.is_empty()) {
+            match kv
+                .split_once(':')
+                .and_then(|(k, v)| Some((k.trim().to_owned(), v.trim().parse().ok()?)))
+            {
+                Some((k, ttl)) => {
+                    entity_ttl.insert(k, ttl);
+                }
+                None => env.push_err(format!("GQL_CACHE_ENTITY_TTL: invalid item <{}>", kv)),
+            }
+        }
+        self.entity_ttl = entity_ttl;
+    }
+
+    ///
+    /// 实体的缓存 TTL(秒)
+    pub fn ttl_of(&self, entity: &str) -> u64 {
+        self.entity_ttl
+            .get(entity)
+            .copied()
+            .unwrap_or(self.default_ttl)
+    }
+
+    ///
+    /// 所有配置中最长的 TTL, 用于实体索引集合的过期时间
+    pub fn max_ttl(&self) -> u64 {
+        self.entity_ttl
+            .values()
+            .copied()
+            .chain(std::iter::once(self.default_ttl))
+            .max()
+            .unwrap_or(0)
+    }
+}
@@ -1,27 +1,120 @@
-use std::env;
+use serde::{Deserialize, Serialize};
+
+use super::source::EnvOverride;
 
 #[allow(unused)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct DaoSetting {
-    // mysql
+    // postgres, 必填
     pub db_main_addr: String,
     pub db_replica_addr: String,
-    // redis 
+    // 副本允许的最大复制延迟(秒)，超过则查询回落主库
+    pub db_replica_max_lag: u64,
+    // 副本健康探测间隔(秒)
+    pub db_replica_check_interval: u64,
+    // 连接池(主库/副本各一个)
+    pub db_pool: DbPoolSetting,
+    // redis
     pub redis_host: Option<String>,
     pub redis_port: Option<String>,
-    pub redis_usr: Option<String>, 
-    pub redis_pwd: Option<String>
+    pub redis_usr: Option<String>,
+    pub redis_pwd: Option<String>,
 }
 
-impl DaoSetting {
-    pub fn new() -> Self {
+impl Default for DaoSetting {
+    fn default() -> Self {
         DaoSetting {
-            db_main_addr: env::var("DB_MAIN_ADDR").unwrap_or("mysql://unknown".to_owned()),
-            db_replica_addr: env::var("DB_REPLICA_ADDR").unwrap_or("".to_owned()),
-            redis_port: env::var("REDIS_PORT").ok(),
-            redis_host: env::var("REDIS_HOST").ok(),
-            redis_usr: env::var("REDIS_USR").ok(),
-            redis_pwd: env::var("REDIS_PWD").ok()
+            db_main_addr: "".to_owned(),
+            db_replica_addr: "".to_owned(),
+            db_replica_max_lag: 10,
+            db_replica_check_interval: 5,
+            db_pool: DbPoolSetting::default(),
+            redis_host: None,
+            redis_port: None,
+            redis_usr: None,
+            redis_pwd: None,
+        }
+    }
+}
+
+impl DaoSetting {
+    pub(super) fn override_env(&mut self, env: &mut EnvOverride) {
+        env.parse("DB_MAIN_ADDR", &mut self.db_main_addr);
+        env.parse("DB_REPLICA_ADDR", &mut self.db_replica_addr);
+        env.parse("DB_REPLICA_MAX_LAG", &mut self.db_replica_max_lag);
+        env.parse(
+            "DB_REPLICA_CHECK_INTERVAL",
+            &mut self.db_replica_check_interval,
+        );
+        self.db_pool.override_env(env);
+        env.opt("REDIS_HOST", &mut self.redis_host);
+        env.opt("REDIS_PORT", &mut self.redis_port);
+        env.opt("REDIS_USR", &mut self.redis_usr);
+        env.opt("REDIS_PWD", &mut self.redis_pwd);
+    }
+
+    ///
+    /// redis 连接地址, 未配置 REDIS_HOST 时返回 None
+    pub fn redis_url(&self) -> Option<String> {
+        let host = self.redis_host.as_ref().filter(|h| !h.is_empty())?;
+        let port = self.redis_port.as_deref().unwrap_or("6379");
+        let auth = match (&self.redis_usr, &self.redis_pwd) {
+            (Some(usr), Some(pwd)) => format!("{}:{}@", usr, pwd),
+            (None, Some(pwd)) => format!(":{}@", pwd),
+            _ => "".to_owned(),
+        };
+        Some(format!("redis://{}{}:{}/", auth, host, port))
+    }
+}
+
+/// 数据库连接池配置, 时间单位均为秒(statement_timeout 为毫秒);
+/// idle/lifetime/statement_timeout 为 0 表示不限制, connect/acquire_timeout 须大于 0
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DbPoolSetting {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub connect_timeout: u64,
+    // 从池中获取连接的等待上限
+    pub acquire_timeout: u64,
+    // 空闲连接回收时间
+    pub idle_timeout: u64,
+    // 连接最长存活时间
+    pub max_lifetime: u64,
+    // 每个连接设置的 postgres statement_timeout(毫秒)
+    pub statement_timeout_ms: u64,
+    // 启动时连接失败的重试次数, 退避: backoff * 2^(n-1), 最长 30 秒
+    pub connect_retries: u32,
+    pub connect_backoff: u64,
+}
+
+impl Default for DbPoolSetting {
+    fn default() -> Self {
+        DbPoolSetting {
+            max_connections: 5,
+            min_connections: 1,
+            connect_timeout: 5,
+            acquire_timeout: 10,
+            idle_timeout: 600,
+            max_lifetime: 1800,
+            statement_timeout_ms: 0,
+            connect_retries: 3,
+            connect_backoff: 1,
         }
     }
-}
\ No newline at end of file
+}
+
+impl DbPoolSetting {
+    fn override_env(&mut self, env: &mut EnvOverride) {
+        env.parse("DB_MAX_CONNECTIONS", &mut self.max_connections);
+        env.parse("DB_MIN_CONNECTIONS", &mut self.min_connections);
+        env.parse("DB_CONNECT_TIMEOUT", &mut self.connect_timeout);
+        env.parse("DB_ACQUIRE_TIMEOUT", &mut self.acquire_timeout);
+        env.parse("DB_IDLE_TIMEOUT", &mut self.idle_timeout);
+        env.parse("DB_MAX_LIFETIME", &mut self.max_lifetime);
+        env.parse("DB_STATEMENT_TIMEOUT_MS", &mut self.statement_timeout_ms);
+        env.parse("DB_CONNECT_RETRIES", &mut self.connect_retries);
+        env.parse("DB_CONNECT_BACKOFF", &mut self.connect_backoff);
+    }
+}
@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::source::EnvOverride;
+use crate::workflow::WorkflowMachines;
+
+/// graphql schema 构建配置, 限制类配置为 0 时表示不限制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GraphqlSetting {
+    /// 开放 create/update/delete mutation 的实体(表名)，其余实体保持只读
+    pub mutation_entities: Vec<String>,
+    // mutation 白名单文件(json数组), 与 mutation_entities 合并
+    pub mutation_file: Option<PathBuf>,
+    // 查询最大嵌套深度(根字段为 1)
+    pub depth_limit: Option<usize>,
+    // 查询最大复杂度(展开 fragment 后的字段数)
+    pub complexity_limit: Option<usize>,
+    // 单次请求 query 文本最大字节数
+    pub max_query_bytes: Option<usize>,
+    // 单次请求最多允许的字段别名数
+    pub max_aliases: Option<usize>,
+    // 状态变更通知的 LISTEN 通道, 为空时不启用订阅推送
+    pub notify_channel: Option<String>,
+    // 方案全文检索使用的 text search configuration(如 zhparser 建立的 chinese), 默认 simple;
+    // 需与 migrations/0003 建索引时一致, 修改后重建索引并重启
+    pub search_ts_config: String,
+    // 分词无法命中时是否以 pg_trgm 子串匹配兜底(中文未装分词插件时需要)
+    pub search_trgm: bool,
+    // 覆盖内置 advanceWorkflow/rollbackWorkflow 状态机的文件, 见 [`WorkflowMachines`]
+    pub workflow_file: Option<PathBuf>,
+    // 内置状态机与 workflow_file 合并的结果
+    #[serde(skip)]
+    pub workflow_machines: Arc<WorkflowMachines>,
+}
+
+impl Default for GraphqlSetting {
+    fn default() -> Self {
+        GraphqlSetting {
+            mutation_entities: vec![],
+            mutation_file: None,
+            depth_limit: Some(10),
+            complexity_limit: Some(1000),
+            max_query_bytes: Some(32 * 1024),
+            max_aliases: Some(30),
+            notify_channel: Some("gql_state_change".to_owned()),
+            search_ts_config: "simple".to_owned(),
+            search_trgm: true,
+            workflow_file: None,
+            workflow_machines: Arc::new(WorkflowMachines::builtin()),
+        }
+    }
+}
+
+impl GraphqlSetting {
+    pub(super) fn override_env(&mut self, env: &mut EnvOverride) {
+        env.list("GQL_MUTATION_ENTITIES", &mut self.mutation_entities);
+        env.opt("GQL_MUTATION_FILE", &mut self.mutation_file);
+        env.opt("GQL_DEPTH_LIMIT", &mut self.depth_limit);
+        env.opt("GQL_COMPLEXITY_LIMIT", &mut self.complexity_limit);
+        env.opt("GQL_MAX_QUERY_BYTES", &mut self.max_query_bytes);
+        env.opt("GQL_MAX_ALIASES", &mut self.max_aliases);
+        env.opt("GQL_NOTIFY_CHANNEL", &mut self.notify_channel);
+        env.parse("GQL_SEARCH_TS_CONFIG", &mut self.search_ts_config);
+        env.parse("GQL_SEARCH_TRGM", &mut self.search_trgm);
+        env.opt("GQL_WORKFLOW_FILE", &mut self.workflow_file);
+    }
+
+    ///
+    /// 0 表示不限制, 合并白名单文件, 加载状态机文件
+    pub(super) fn normalize(&mut self, errs: &mut Vec<String>) {
+        for limit in [
+            &mut self.depth_limit,
+            &mut self.complexity_limit,
+            &mut self.max_query_bytes,
+            &mut self.max_aliases,
+        ] {
+            *limit = limit.filter(|v| *v > 0);
+        }
+        self.notify_channel = self.notify_channel.take().filter(|s| !s.is_empty());
+        if self.search_ts_config.is_empty() {
+            self.search_ts_config = "simple".to_owned();
+        }
+        if let Some(f) = &self.mutation_file {
+            match Self::load_mutation_file(f) {
+                Ok(v) => self.mutation_entities.extend(v),
+                Err(e) => errs.push(format!("GQL_MUTATION_FILE <{}>: {}", f.display(), e)),
+            }
+        }
+        self.workflow_machines = Arc::new(match &self.workflow_file {
+            Some(f) => match WorkflowMachines::from_file(f) {
+                Ok(v) => v,
+                Err(e) => {
+                    errs.push(format!("GQL_WORKFLOW_FILE <{}>: {}", f.display(), e));
+                    WorkflowMachines::builtin()
+                }
+            },
+            None => WorkflowMachines::builtin(),
+        });
+        self.mutation_entities.sort();
+        self.mutation_entities.dedup();
+    }
+
+    ///
+    /// 从文件读取 mutation 白名单, 格式: ["solution_draft", "feature_setting"]
+    fn load_mutation_file(f: &PathBuf) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let js_cont: String = std::fs::read_to_string(f)?;
+        let v: Vec<String> = serde_json::from_str(js_cont.as_str())?;
+        Ok(v)
+    }
+}
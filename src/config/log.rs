@@ -1,44 +1,46 @@
-use std::{env, path::PathBuf, str::FromStr};
+use std::{env, str::FromStr};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tracing::level_filters::LevelFilter;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, Layer};
+use tracing_subscriber::{
+    layer::{Layered, SubscriberExt},
+    reload,
+    util::SubscriberInitExt,
+    Layer, Registry,
+};
+
+use super::source::EnvOverride;
+
+/// 日志级别热更新句柄
+pub type LogReloadHandle = reload::Handle<LevelFilter, Registry>;
+type LogRegistry = Layered<reload::Layer<LevelFilter, Registry>, Registry>;
 
 #[allow(unused)]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct LogConfig {
     pub enable_log_file: bool,
     pub enable_stdout: bool,
     pub level: String,
     // pub enable_loki: bool, (废弃了，使用vector来管理日志跟优雅，多前端后端)
 }
-impl LogConfig {
-    ///
-    /// 从配置文件初始化配置
-    pub fn try_from_file(f: Option<PathBuf>) -> Result<Self, Box<dyn std::error::Error>> {
-        if let Some(f_) = f {
-            if f_.is_file() {
-                let js_cont: String = std::fs::read_to_string(&f_)?;
-                let cfg: LogConfig = serde_json::from_str(js_cont.as_str())
-                    .expect(format!("parse log config {} failed!", f_.display()).as_str());
-                return Ok(cfg);
-            }
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            enable_log_file: true,
+            enable_stdout: true,
+            level: "info".to_owned(),
         }
-        Err("No valid configuration file provided".into())
     }
+}
 
+impl LogConfig {
     ///
-    /// 从环境初始化log配置
-    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
-        Ok(Self {
-            enable_log_file: env::var("ENABLE_LOG_FILE")
-                .unwrap_or_else(|_| "true".to_string())
-                .parse()?,
-            enable_stdout: env::var("ENABLE_STDOUT")
-                .unwrap_or_else(|_| "true".to_string())
-                .parse()?,
-            level: env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string()),
-        })
+    /// 环境变量覆盖log配置
+    pub(super) fn override_env(&mut self, env: &mut EnvOverride) {
+        env.parse("ENABLE_LOG_FILE", &mut self.enable_log_file);
+        env.parse("ENABLE_STDOUT", &mut self.enable_stdout);
+        env.parse("LOG_LEVEL", &mut self.level);
     }
 }
 
@@ -46,9 +48,9 @@ impl LogConfig {
     /// 初始化日志
     /// 这里使用tracing替代log
     /// 后续方便扩展span监控
-    pub fn init_logger(cfg: &LogConfig) -> Result<(), Box<dyn std::error::Error>> {
-        let mut layers: Vec<Box<dyn Layer<tracing_subscriber::Registry> + Send + Sync>> = vec![];
-        println!("---\nLog {:#?}\n---", cfg);
+    /// 日志级别为全局过滤, 返回的句柄用于配置热更新
+    pub fn init_logger(cfg: &LogConfig) -> Result<LogReloadHandle, Box<dyn std::error::Error>> {
+        let mut layers: Vec<Box<dyn Layer<LogRegistry> + Send + Sync>> = vec![];
         // log-file
         if cfg.enable_log_file {
             // 获取当前目录
@@ -64,30 +66,26 @@ impl LogConfig {
                     tracing_appender::non_blocking::NonBlockingBuilder::default()
                         .lossy(false)
                         .finish(file_appender);
-                let layer: Box<dyn Layer<tracing_subscriber::Registry> + Send + Sync> =
+                let layer: Box<dyn Layer<LogRegistry> + Send + Sync> =
                     tracing_subscriber::fmt::layer()
                         .with_ansi(false)
                         .with_writer(non_blocking)
-                        .with_filter(
-                            LevelFilter::from_str(&cfg.level)
-                                .unwrap_or_else(|e| "info".parse().expect("ENSURE")),
-                        )
                         .boxed();
                 layers.push(layer);
             }
         }
         // stdout
         if cfg.enable_stdout {
-            let layer = tracing_subscriber::fmt::layer()
-                .with_filter(
-                    LevelFilter::from_str(&cfg.level)
-                        .unwrap_or_else(|e| "info".parse().expect("ENSURE")),
-                )
-                .boxed();
+            let layer = tracing_subscriber::fmt::layer().boxed();
 
             layers.push(layer);
         }
-        tracing_subscriber::registry().with(layers).init();
-        Ok(())
+        let level = LevelFilter::from_str(&cfg.level).unwrap_or(LevelFilter::INFO);
+        let (filter, handle) = reload::Layer::new(level);
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(layers)
+            .init();
+        Ok(handle)
     }
 }
@@ -1,79 +1,140 @@
+pub mod auth;
+pub mod cache;
 pub mod dao;
+pub mod graphql;
 pub mod log;
+pub mod polling;
+pub mod reload;
+pub mod source;
+pub mod validate;
+use self::log::LogConfig;
+use auth::AuthSetting;
+use cache::CacheSetting;
 use dao::DaoSetting;
+use graphql::GraphqlSetting;
+use polling::PollingSetting;
+use serde::{Deserialize, Serialize};
+use source::{ConfigSource, EnvOverride};
 use static_remote::S3RegionSetting;
-use std::env;
 use tracing;
+use validate::ConfigError;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct SvrBase {
     pub svr_name: String,
     pub port: u16,
     pub host: String,
+    // 错误响应一律返回 200(旧客户端只看 body 中的 code)
+    pub legacy_status: bool,
+    // 配置文件变更轮询间隔(秒), 0 表示只响应 SIGHUP
+    pub config_watch_interval: u64,
 }
 
-#[derive(Debug, Clone)]
+impl Default for SvrBase {
+    fn default() -> Self {
+        SvrBase {
+            svr_name: "UNKNOWN SERVICE".to_owned(),
+            port: 12121,
+            host: "0.0.0.0".to_owned(),
+            legacy_status: false,
+            config_watch_interval: 5,
+        }
+    }
+}
+
+impl SvrBase {
+    fn override_env(&mut self, env: &mut EnvOverride) {
+        env.parse("SERVICE_NAME", &mut self.svr_name);
+        env.parse("PORT", &mut self.port);
+        env.parse("HOST", &mut self.host);
+        env.parse("HTTP_LEGACY_STATUS", &mut self.legacy_status);
+        env.parse("CONFIG_WATCH_INTERVAL", &mut self.config_watch_interval);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Metrics {
     pub end_point: String,
 }
 
 ///app运行全局配置上下文
 #[allow(unused)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct RuntimeSetting {
     pub base: SvrBase,
     pub dao: DaoSetting,
+    pub graphql: GraphqlSetting,
+    pub cache: CacheSetting,
+    pub auth: AuthSetting,
+    pub polling: PollingSetting,
+    pub log: LogConfig,
     pub metrics: Option<Metrics>,
+    // 只在启动时加载, 见 [`RuntimeSetting::load_s3`]
+    #[serde(skip)]
     pub s3: Option<S3RegionSetting>,
 }
 
-impl Default for RuntimeSetting {
-    fn default() -> Self {
-        println!("env: {:?}", env::var("PORT"));
+/// 配置文件中的分组
+const SECTIONS: &[&str] = &[
+    "base", "dao", "graphql", "cache", "auth", "polling", "log", "metrics",
+];
+
+impl RuntimeSetting {
+    ///
+    /// 配置文件(见 [`ConfigSource`])按分组反序列化, 再以环境变量覆盖, 最后校验;
+    /// 一次列出全部错误. 不修改进程环境变量, 可在运行时重复调用
+    pub fn load(source: &ConfigSource) -> Result<Self, ConfigError> {
+        let mut errs = vec![];
+        source.check_sections(SECTIONS, &mut errs);
         let mut conf = RuntimeSetting {
-            dao: DaoSetting::new(),
-            base: SvrBase {
-                svr_name: env::var("SERVICE_NAME").unwrap_or("UNKNOWN SERVICE".to_owned()),
-                port: env::var("PORT")
-                    .unwrap_or("12121".to_owned())
-                    .parse::<u16>()
-                    .unwrap(),
-                host: env::var("HOST").unwrap_or("0.0.0.0".to_owned()),
-            },
-            metrics: None,
+            base: source.section("base", &mut errs),
+            dao: source.section("dao", &mut errs),
+            graphql: source.section("graphql", &mut errs),
+            cache: source.section("cache", &mut errs),
+            auth: source.section("auth", &mut errs),
+            polling: source.section("polling", &mut errs),
+            log: source.section("log", &mut errs),
+            metrics: source.section("metrics", &mut errs),
             s3: None,
         };
-        println!("port: {:?}", conf.base);
-        #[cfg(feature = "metrics")]
-        conf.try_load_metrics();
-        conf.try_load_s3();
-        println!("---\n{:#?}\n---", conf);
-        return conf;
-    }
-}
-
-impl RuntimeSetting {
-    fn try_load_metrics(&mut self) {
-        // <endpoint>->Required base param
-        if let Ok(endpoint) = env::var("METRIC_ENDPOINT") {
-            self.metrics = Some(Metrics {
-                end_point: endpoint,
-            });
-            return;
+        {
+            let mut env = EnvOverride::new(&mut errs);
+            conf.base.override_env(&mut env);
+            conf.dao.override_env(&mut env);
+            conf.graphql.override_env(&mut env);
+            conf.cache.override_env(&mut env);
+            conf.auth
+                .override_env(&mut env, source.contains("auth", "enable"));
+            conf.polling.override_env(&mut env);
+            conf.log.override_env(&mut env);
+            // <endpoint>->Required base param
+            if let Some(end_point) = env.var("METRIC_ENDPOINT") {
+                conf.metrics = Some(Metrics { end_point });
+            }
+        }
+        conf.graphql.normalize(&mut errs);
+        if let Err(e) = conf.validate() {
+            errs.extend(e.0);
+        }
+        if errs.is_empty() {
+            Ok(conf)
+        } else {
+            Err(ConfigError(errs))
         }
-        tracing::warn!("METRIC_ENDPOINT is not set.RuntimeSetting init metrics config failed!");
-        return;
     }
 
-    fn try_load_s3(&mut self) {
-        if let Ok(v) = dotenv::from_filename(".s3"){
-            let s3 = S3RegionSetting::from_env();
-            self.s3 = Some(s3);
-            tracing::info!("load s3 config from .s3 file: {:?}", v);
-        }
-        else {
-            tracing::warn!("failed load s3 config!");
+    ///
+    /// s3 配置(含 access key)仍来自 .s3 文件(与 .env 相同由 dotenv 加载),
+    /// 只在启动时加载, 不参与热更新
+    pub fn load_s3(&mut self) {
+        match dotenv::from_filename(".s3") {
+            Ok(f) => {
+                self.s3 = Some(S3RegionSetting::from_env());
+                tracing::info!("load s3 config from: {:?}", f);
+            }
+            Err(_) => tracing::warn!("failed load s3 config!"),
         }
-        
     }
 }
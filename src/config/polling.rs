@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use super::source::{secs, EnvOverride};
+
+/// 内置轮询任务执行器配置(需开启 `polling-executor` feature), 时长在配置文件中以秒表示
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PollingSetting {
+    pub enable: bool,
+    // 只认领该协议类型的任务
+    pub protocol_type: i16,
+    // 同时执行的任务数上限
+    pub concurrency: usize,
+    // 认领任务的轮询间隔
+    #[serde(with = "secs")]
+    pub interval: Duration,
+    // 单次请求超时
+    #[serde(with = "secs")]
+    pub request_timeout: Duration,
+    // 重试退避: base * 2^(n-1), 不超过 max
+    #[serde(with = "secs")]
+    pub backoff_base: Duration,
+    #[serde(with = "secs")]
+    pub backoff_max: Duration,
+    // 执行中的任务超过该时间未更新, 视为执行器已退出, 允许重新认领
+    #[serde(with = "secs")]
+    pub lease: Duration,
+    // 记录到 st_polling_log 的响应体最大字节数
+    pub max_body_bytes: usize,
+    // 按完成条件判定外部 worker 写入的 st_polling_log, 见 `polling::evaluator`
+    pub evaluate_logs: bool,
+    // st_polling_task.status 的取值
+    pub status_codes: TaskStatusCodes,
+}
+
+/// `st_polling_task.status` 各状态的取值
+///
+/// 该列与外部 asynq worker 共用, 取值由 worker 定义(不在本仓库中), 默认值 0-4 未与 worker 核对,
+/// 部署前需按 worker 的状态常量配置, 否则两边会互相误判任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TaskStatusCodes {
+    pub pending: i16,
+    pub running: i16,
+    pub success: i16,
+    pub failed: i16,
+    pub retrying: i16,
+}
+
+impl Default for TaskStatusCodes {
+    fn default() -> Self {
+        TaskStatusCodes {
+            pending: 0,
+            running: 1,
+            success: 2,
+            failed: 3,
+            retrying: 4,
+        }
+    }
+}
+
+impl Default for PollingSetting {
+    fn default() -> Self {
+        PollingSetting {
+            enable: false,
+            protocol_type: 1,
+            concurrency: 8,
+            interval: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(30),
+            backoff_base: Duration::from_secs(2),
+            backoff_max: Duration::from_secs(300),
+            lease: Duration::from_secs(600),
+            max_body_bytes: 64 * 1024,
+            evaluate_logs: false,
+            status_codes: TaskStatusCodes::default(),
+        }
+    }
+}
+
+impl PollingSetting {
+    pub(super) fn override_env(&mut self, env: &mut EnvOverride) {
+        env.parse("POLLING_ENABLE", &mut self.enable);
+        env.parse("POLLING_PROTOCOL_TYPE", &mut self.protocol_type);
+        env.parse("POLLING_CONCURRENCY", &mut self.concurrency);
+        env.secs("POLLING_INTERVAL", &mut self.interval);
+        env.secs("POLLING_REQUEST_TIMEOUT", &mut self.request_timeout);
+        env.secs("POLLING_BACKOFF_BASE", &mut self.backoff_base);
+        env.secs("POLLING_BACKOFF_MAX", &mut self.backoff_max);
+        env.secs("POLLING_LEASE", &mut self.lease);
+        env.parse("POLLING_MAX_BODY_BYTES", &mut self.max_body_bytes);
+        env.parse("POLLING_EVALUATE_LOGS", &mut self.evaluate_logs);
+        let codes = &mut self.status_codes;
+        env.parse("POLLING_STATUS_PENDING", &mut codes.pending);
+        env.parse("POLLING_STATUS_RUNNING", &mut codes.running);
+        env.parse("POLLING_STATUS_SUCCESS", &mut codes.success);
+        env.parse("POLLING_STATUS_FAILED", &mut codes.failed);
+        env.parse("POLLING_STATUS_RETRYING", &mut codes.retrying);
+    }
+
+    ///
+    /// 第 `retry` 次重试前的等待时间
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.backoff_base
+            .saturating_mul(factor)
+            .min(self.backoff_max)
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use serde_json::Value;
+use tokio::sync::watch;
+use tracing::level_filters::LevelFilter;
+
+use super::{log::LogReloadHandle, source::ConfigSource, RuntimeSetting};
+use crate::error;
+use crate::util::json_diff;
+
+/// 无需重启即可生效的配置(配置 json 路径前缀):
+/// 请求处理时读取当前配置的项, graphql schema 在 graphql 配置变更时重建,
+/// 轮询执行器每轮读取当前配置. 监听地址、连接、鉴权、日志输出、指标等变更后需重启
+const HOT_PATHS: &[&str] = &[
+    "/log/level",
+    "/base/legacy_status",
+    "/base/config_watch_interval",
+    "/cache/",
+    "/graphql/mutation_entities",
+    "/graphql/mutation_file",
+    "/graphql/depth_limit",
+    "/graphql/complexity_limit",
+    "/graphql/max_query_bytes",
+    "/graphql/max_aliases",
+    "/graphql/search_trgm",
+    "/graphql/workflow_file",
+    "/polling/",
+];
+
+/// 处理器共享的运行配置, 热更新时整体替换; 需要随配置重建状态的模块可订阅变更
+#[derive(Clone)]
+pub struct SharedSetting(Arc<watch::Sender<Arc<RuntimeSetting>>>);
+
+impl SharedSetting {
+    pub fn new(setting: RuntimeSetting) -> Self {
+        SharedSetting(Arc::new(watch::Sender::new(Arc::new(setting))))
+    }
+
+    ///
+    /// 当前配置快照, 单个请求内应只取一次
+    pub fn load(&self) -> Arc<RuntimeSetting> {
+        self.0.borrow().clone()
+    }
+
+    ///
+    /// 订阅配置变更, 每次热更新生效后通知
+    pub fn subscribe(&self) -> watch::Receiver<Arc<RuntimeSetting>> {
+        self.0.subscribe()
+    }
+
+    fn store(&self, setting: RuntimeSetting) {
+        self.0.send_replace(Arc::new(setting));
+    }
+}
+
+///
+/// 启动配置监听: 收到 SIGHUP 或配置文件、GQL_MUTATION_FILE、GQL_WORKFLOW_FILE 修改时间变化时重新加载,
+/// 校验失败时保留当前配置
+pub fn spawn_watcher(shared: SharedSetting, source: ConfigSource, log: LogReloadHandle) {
+    tokio::spawn(async move {
+        let mut watcher = Watcher {
+            shared,
+            log,
+            source,
+        };
+        let mut hup = hangup();
+        let mut mtimes = watcher.modified();
+        loop {
+            let interval = watcher.shared.load().base.config_watch_interval;
+            let reason = tokio::select! {
+                _ = recv_hangup(&mut hup) => "SIGHUP",
+                _ = tick(interval) => {
+                    if watcher.modified() == mtimes {
+                        continue;
+                    }
+                    "file changed"
+                }
+            };
+            watcher.reload(reason);
+            mtimes = watcher.modified();
+        }
+    });
+}
+
+struct Watcher {
+    shared: SharedSetting,
+    log: LogReloadHandle,
+    source: ConfigSource,
+}
+
+impl Watcher {
+    ///
+    /// 配置文件及当前配置引用的可热更新文件(mutation/workflow)的修改时间
+    fn modified(&self) -> Vec<Option<SystemTime>> {
+        let graphql = &self.shared.load().graphql;
+        let files = [&graphql.mutation_file, &graphql.workflow_file];
+        let mut mtimes = self.source.modified();
+        mtimes.extend(
+            files
+                .into_iter()
+                .flatten()
+                .map(|f| std::fs::metadata(f).and_then(|m| m.modified()).ok()),
+        );
+        mtimes
+    }
+
+    fn reload(&mut self, reason: &str) {
+        tracing::info!("[config] reload on {}", reason);
+        self.source = match self.source.reload() {
+            Ok(source) => source,
+            Err(e) => {
+                tracing::error!("[config] reload failed, keep current config:\n{}", e);
+                return;
+            }
+        };
+        let mut setting = match RuntimeSetting::load(&self.source) {
+            Ok(setting) => setting,
+            Err(e) => {
+                tracing::error!("[config] invalid config, keep current config:\n{}", e);
+                return;
+            }
+        };
+        let current = self.shared.load();
+        setting.s3 = current.s3.clone();
+        if setting.graphql.search_ts_config != current.graphql.search_ts_config {
+            tracing::warn!(
+                "[config] GQL_SEARCH_TS_CONFIG changed, rebuild the indexes in migrations/0003_solution_search.sql with the new configuration before restarting"
+            );
+        }
+        let changes = json_diff(&current.redacted(), &setting.redacted());
+        if changes.is_empty() {
+            tracing::info!("[config] nothing changed");
+        }
+        for c in changes {
+            let (old, new) = (display(c.before), display(c.after));
+            if HOT_PATHS.iter().any(|p| c.path.starts_with(p)) {
+                tracing::info!("[config] {}: {} -> {}", c.path, old, new);
+            } else {
+                tracing::warn!(
+                    "[config] {}: {} -> {} (takes effect after restart)",
+                    c.path,
+                    old,
+                    new
+                );
+            }
+        }
+        if setting.log.level != current.log.level {
+            let level = LevelFilter::from_str(&setting.log.level).unwrap_or(LevelFilter::INFO);
+            if let Err(e) = self.log.modify(|f| *f = level) {
+                tracing::error!("[config] reload log level failed: {:?}", e);
+            }
+        }
+        error::set_legacy_status(setting.base.legacy_status);
+        // 检索语句须与已建索引的表达式一致, 只在重启时生效
+        setting.graphql.search_ts_config = current.graphql.search_ts_config.clone();
+        self.shared.store(setting);
+    }
+}
+
+///
+/// 变更前后的值(已打码), 见 [`RuntimeSetting::redacted`]
+fn display(v: Option<Value>) -> String {
+    v.map(|v| v.to_string())
+        .unwrap_or_else(|| "<unset>".to_owned())
+}
+
+async fn tick(secs: u64) {
+    if secs == 0 {
+        std::future::pending::<()>().await;
+    }
+    tokio::time::sleep(Duration::from_secs(secs)).await;
+}
+
+#[cfg(unix)]
+type Hangup = Option<tokio::signal::unix::Signal>;
+#[cfg(not(unix))]
+type Hangup = ();
+
+#[cfg(unix)]
+fn hangup() -> Hangup {
+    use tokio::signal::unix::{signal, SignalKind};
+    signal(SignalKind::hangup())
+        .map_err(|e| tracing::warn!("[config] listen SIGHUP failed: {:?}", e))
+        .ok()
+}
+
+#[cfg(not(unix))]
+fn hangup() -> Hangup {}
+
+async fn recv_hangup(hup: &mut Hangup) {
+    #[cfg(unix)]
+    if let Some(sig) = hup {
+        sig.recv().await;
+        return;
+    }
+    std::future::pending::<()>().await;
+}
@@ -0,0 +1,254 @@
+use std::{
+    env,
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::validate::ConfigError;
+
+/// 配置文件目录, 可用 CONFIG_DIR 覆盖
+const DEFAULT_CONFIG_DIR: &str = "envs";
+/// 各 profile 共用的配置文件名
+const BASE_PROFILE: &str = "base";
+
+/// 分层配置来源
+///
+/// 优先级(高 -> 低): 环境变量 > .env > `envs/<profile>.{toml,json}` > `envs/base.{toml,json}`,
+/// 配置文件按分组反序列化为对应的 Setting(键即字段名, 支持嵌套表), 环境变量只读取不写入:
+///
+/// ```toml
+/// [base]
+/// port = 12122
+/// [dao]
+/// db_main_addr = "postgresql://..."
+/// [dao.db_pool]
+/// max_connections = 10
+/// [graphql]
+/// mutation_entities = ["solution_draft", "feature_setting"]
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSource {
+    // dev/test/prod, 来自 APP_PROFILE
+    pub profile: String,
+    pub dir: PathBuf,
+    // 实际加载的配置文件, 按优先级排列
+    pub files: Vec<PathBuf>,
+    // 按优先级深度合并后的配置
+    value: Value,
+}
+
+impl ConfigSource {
+    ///
+    /// 读取当前 profile 的配置文件; 需在 dotenv 之后调用
+    pub fn load() -> Result<Self, ConfigError> {
+        let profile = env::var("APP_PROFILE")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "dev".to_owned());
+        let dir =
+            PathBuf::from(env::var("CONFIG_DIR").unwrap_or_else(|_| DEFAULT_CONFIG_DIR.to_owned()));
+        Self::read(profile, dir)
+    }
+
+    ///
+    /// 重新读取配置文件(profile 与目录不变)
+    pub fn reload(&self) -> Result<Self, ConfigError> {
+        Self::read(self.profile.clone(), self.dir.clone())
+    }
+
+    fn read(profile: String, dir: PathBuf) -> Result<Self, ConfigError> {
+        let mut source = ConfigSource {
+            profile,
+            dir,
+            files: vec![],
+            value: Value::Object(Default::default()),
+        };
+        let mut errs = vec![];
+        // 低优先级先合并
+        for f in source.candidates().into_iter().rev() {
+            if !f.is_file() {
+                continue;
+            }
+            match read_file(&f) {
+                Ok(v @ Value::Object(_)) => {
+                    merge(&mut source.value, v);
+                    source.files.insert(0, f);
+                }
+                Ok(_) => errs.push(format!("{}: root must be a table", f.display())),
+                Err(e) => errs.push(format!("{}: {}", f.display(), e)),
+            }
+        }
+        if errs.is_empty() {
+            Ok(source)
+        } else {
+            Err(ConfigError(errs))
+        }
+    }
+
+    ///
+    /// 按优先级排列的候选配置文件, 不要求存在
+    pub fn candidates(&self) -> Vec<PathBuf> {
+        [self.profile.as_str(), BASE_PROFILE]
+            .iter()
+            .flat_map(|name| ["toml", "json"].map(|ext| self.dir.join(format!("{}.{}", name, ext))))
+            .collect()
+    }
+
+    ///
+    /// 候选配置文件的修改时间, 用于轮询检测变更(含新建/删除)
+    pub fn modified(&self) -> Vec<Option<SystemTime>> {
+        self.candidates()
+            .iter()
+            .map(|f| std::fs::metadata(f).and_then(|m| m.modified()).ok())
+            .collect()
+    }
+
+    ///
+    /// 反序列化一个分组, 未配置时取默认值, 出错时记入 errs 并取默认值
+    pub(super) fn section<T: DeserializeOwned + Default>(
+        &self,
+        name: &str,
+        errs: &mut Vec<String>,
+    ) -> T {
+        match self.value.get(name) {
+            None | Some(Value::Null) => T::default(),
+            Some(v) => T::deserialize(v).unwrap_or_else(|e| {
+                errs.push(format!("[{}] {}", name, e));
+                T::default()
+            }),
+        }
+    }
+
+    ///
+    /// 分组中是否显式配置了某个键
+    pub(super) fn contains(&self, section: &str, key: &str) -> bool {
+        self.value
+            .get(section)
+            .and_then(|s| s.get(key))
+            .is_some_and(|v| !v.is_null())
+    }
+
+    ///
+    /// 配置文件中不认识的分组(多为拼写错误)
+    pub(super) fn check_sections(&self, known: &[&str], errs: &mut Vec<String>) {
+        if let Some(root) = self.value.as_object() {
+            for key in root.keys().filter(|k| !known.contains(&k.as_str())) {
+                errs.push(format!("unknown config section [{}]", key));
+            }
+        }
+    }
+}
+
+fn read_file(f: &Path) -> Result<Value, Box<dyn std::error::Error>> {
+    let cont = std::fs::read_to_string(f)?;
+    let v = match f.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(&cont)?,
+        _ => serde_json::from_str(&cont)?,
+    };
+    Ok(v)
+}
+
+///
+/// 表逐键合并, 其余类型(含数组)整体覆盖
+fn merge(base: &mut Value, over: Value) {
+    match (base, over) {
+        (Value::Object(b), Value::Object(o)) => {
+            for (k, v) in o {
+                match b.get_mut(&k) {
+                    Some(bv) => merge(bv, v),
+                    None => {
+                        b.insert(k, v);
+                    }
+                }
+            }
+        }
+        (b, o) => *b = o,
+    }
+}
+
+/// 以环境变量覆盖配置文件中的值: 设置了才覆盖, 解析失败记入错误
+pub(super) struct EnvOverride<'a> {
+    errs: &'a mut Vec<String>,
+}
+
+impl<'a> EnvOverride<'a> {
+    pub fn new(errs: &'a mut Vec<String>) -> Self {
+        EnvOverride { errs }
+    }
+
+    pub fn var(&self, key: &str) -> Option<String> {
+        env::var(key).ok()
+    }
+
+    pub fn push_err(&mut self, e: String) {
+        self.errs.push(e);
+    }
+
+    pub fn parse<T: FromStr>(&mut self, key: &str, target: &mut T)
+    where
+        T::Err: Display,
+    {
+        if let Some(v) = self.var(key) {
+            match v.parse() {
+                Ok(v) => *target = v,
+                Err(e) => self.errs.push(format!("{}=<{}>: {}", key, v, e)),
+            }
+        }
+    }
+
+    ///
+    /// 空字符串视为 None
+    pub fn opt<T: FromStr>(&mut self, key: &str, target: &mut Option<T>)
+    where
+        T::Err: Display,
+    {
+        match self.var(key) {
+            Some(v) if v.is_empty() => *target = None,
+            Some(v) => match v.parse() {
+                Ok(v) => *target = Some(v),
+                Err(e) => self.errs.push(format!("{}=<{}>: {}", key, v, e)),
+            },
+            None => {}
+        }
+    }
+
+    ///
+    /// 列表按 `,` 分隔
+    pub fn list(&mut self, key: &str, target: &mut Vec<String>) {
+        if let Some(v) = self.var(key) {
+            *target = v
+                .split(',')
+                .map(|s| s.trim().to_owned())
+                .filter(|s| !s.is_empty())
+                .collect();
+        }
+    }
+
+    ///
+    /// 时长, 单位秒
+    pub fn secs(&mut self, key: &str, target: &mut Duration) {
+        let mut secs = target.as_secs();
+        self.parse(key, &mut secs);
+        *target = Duration::from_secs(secs);
+    }
+}
+
+/// Duration 在配置文件中以秒表示
+pub(super) mod secs {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(d.as_secs())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        u64::deserialize(d).map(Duration::from_secs)
+    }
+}
@@ -1,30 +1,70 @@
-use std::{error::Error, time::Duration};
+use std::time::Duration;
 use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+use crate::config::{dao::DbPoolSetting, validate::mask_url};
 use crate::dao::seaorm_mysql::AppState;
+use crate::error::{DError, LogicErr};
+pub mod notify;
+pub mod replica;
 pub mod seaorm_mysql;
 
-const DEFAULT_SQL_MAX_CONNECTIONS: u32 = 5;
-const DEFAULT_SQL_MIN_CONNECTIONS: u32 = 1;
-const DEFAULT_SQL_CONNECT_TIMEOUT: u64 = 5;
+/// 连接重试退避上限
+const MAX_CONNECT_BACKOFF: Duration = Duration::from_secs(30);
 
+///
+/// 按连接池配置建立连接, 失败时按退避重试, 全部失败返回 [`LogicErr::ConnectFailed`]
 pub async fn init_sql_connection(
     sql_addr: &str,
+    setting: &DbPoolSetting,
     log_level: log::LevelFilter,
-) -> Result<DatabaseConnection, Box<dyn Error>> {
+) -> Result<DatabaseConnection, DError> {
+    let secs = |v: u64| (v > 0).then(|| Duration::from_secs(v));
     let mut conn_core_opt = ConnectOptions::new(sql_addr);
     conn_core_opt
-        .max_connections(DEFAULT_SQL_MAX_CONNECTIONS)
-        .min_connections(DEFAULT_SQL_MIN_CONNECTIONS)
-        .connect_timeout(Duration::from_secs(DEFAULT_SQL_CONNECT_TIMEOUT))
+        .max_connections(setting.max_connections)
+        .min_connections(setting.min_connections)
+        .connect_timeout(Duration::from_secs(setting.connect_timeout))
+        .acquire_timeout(Duration::from_secs(setting.acquire_timeout))
         .sqlx_logging(true)
         .sqlx_logging_level(log_level);
-    // conn_core_opt
-    let conn_core = Database::connect(conn_core_opt).await.expect(
-        format!(
-            "Connect to db=<{}> failed! Check the db connectable first!",
-            sql_addr
-        )
-        .as_str(),
-    );
-    Ok(conn_core)
+    if let Some(v) = secs(setting.idle_timeout) {
+        conn_core_opt.idle_timeout(v);
+    }
+    if let Some(v) = secs(setting.max_lifetime) {
+        conn_core_opt.max_lifetime(v);
+    }
+    if setting.statement_timeout_ms > 0 {
+        let timeout = setting.statement_timeout_ms.to_string();
+        conn_core_opt.map_sqlx_postgres_opts(move |opts| {
+            opts.options([("statement_timeout", timeout.as_str())])
+        });
+    }
+    let mut attempt = 0;
+    loop {
+        match Database::connect(conn_core_opt.clone()).await {
+            Ok(conn) => return Ok(conn),
+            Err(e) if attempt < setting.connect_retries => {
+                attempt += 1;
+                let backoff = Duration::from_secs(setting.connect_backoff)
+                    .saturating_mul(2u32.saturating_pow(attempt - 1))
+                    .min(MAX_CONNECT_BACKOFF);
+                tracing::warn!(
+                    "Connect to db=<{}> failed, retry {}/{} in {:?}: {:?}",
+                    mask_url(sql_addr),
+                    attempt,
+                    setting.connect_retries,
+                    backoff,
+                    e
+                );
+                tokio::time::sleep(backoff).await;
+            }
+            Err(e) => {
+                return Err(DError::Custom(LogicErr::ConnectFailed(format!(
+                    "<{}> after {} attempts: {}",
+                    mask_url(sql_addr),
+                    attempt + 1,
+                    e
+                ))))
+            }
+        }
+    }
 }
@@ -0,0 +1,79 @@
+use sea_orm::sqlx::postgres::PgListener;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// 广播缓冲区大小, 订阅端消费过慢时丢弃最旧的通知
+const STATE_BUS_CAPACITY: usize = 1024;
+/// LISTEN 连接断开后的重连间隔
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 状态变更通知, 由 `migrations/0001_gql_state_notify.sql` 中的触发器通过 pg_notify 发出
+///
+/// ```json
+/// {"table": "st_workflow", "id": 1, "state": "running", "prevState": "init", "at": "2024-01-01T00:00:00+08:00"}
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateChange {
+    pub table: String,
+    pub id: i64,
+    pub state: Option<String>,
+    pub prev_state: Option<String>,
+    pub at: Option<String>,
+}
+
+/// 数据库状态变更的进程内广播, 每个 graphql 订阅持有一个接收端
+#[derive(Debug, Clone)]
+pub struct StateBus {
+    tx: broadcast::Sender<StateChange>,
+}
+
+impl Default for StateBus {
+    fn default() -> Self {
+        let (tx, _) = broadcast::channel(STATE_BUS_CAPACITY);
+        StateBus { tx }
+    }
+}
+
+impl StateBus {
+    pub fn subscribe(&self) -> broadcast::Receiver<StateChange> {
+        self.tx.subscribe()
+    }
+
+    ///
+    /// 启动 LISTEN 后台任务: 独立连接监听 `channel`, 断开后自动重连
+    pub fn spawn_listener(db_addr: String, channel: String) -> Self {
+        let bus = StateBus::default();
+        let tx = bus.tx.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = listen(&db_addr, &channel, &tx).await {
+                    tracing::warn!("[notify] listen <{}> interrupted: {:?}", channel, e);
+                }
+                tokio::time::sleep(RECONNECT_INTERVAL).await;
+            }
+        });
+        bus
+    }
+}
+
+async fn listen(
+    db_addr: &str,
+    channel: &str,
+    tx: &broadcast::Sender<StateChange>,
+) -> Result<(), sea_orm::sqlx::Error> {
+    let mut listener = PgListener::connect(db_addr).await?;
+    listener.listen(channel).await?;
+    tracing::info!("[notify] listening on <{}>", channel);
+    loop {
+        let notification = listener.recv().await?;
+        match serde_json::from_str::<StateChange>(notification.payload()) {
+            // 无订阅者时 send 返回错误, 忽略即可
+            Ok(change) => {
+                let _ = tx.send(change);
+            }
+            Err(e) => tracing::warn!("[notify] bad payload <{}>: {:?}", notification.payload(), e),
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, DbErr, Statement};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// 副本复制延迟(秒); 非 standby 或已追平 wal 时视为0
+const REPLICA_LAG_SQL: &str = r#"
+SELECT CASE
+    WHEN NOT pg_is_in_recovery() OR pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0
+    ELSE COALESCE(EXTRACT(EPOCH FROM (now() - pg_last_xact_replay_timestamp())), 0)
+END::float8 AS lag
+"#;
+
+/// 只读副本健康状态，由后台任务定时探测
+#[derive(Debug, Clone, Default)]
+pub struct ReplicaHealth {
+    healthy: Arc<AtomicBool>,
+}
+
+impl ReplicaHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    ///
+    /// 启动副本探测: 不可达或延迟超过 `max_lag` 时标记为不可用, 查询回落主库
+    pub fn spawn_check(conn: DatabaseConnection, max_lag: Duration, interval: Duration) -> Self {
+        let health = ReplicaHealth::default();
+        let flag = health.healthy.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let healthy = match probe_lag(&conn).await {
+                    Ok(lag) if lag <= max_lag.as_secs_f64() => true,
+                    Ok(lag) => {
+                        tracing::warn!("db replica lagging {:.1}s behind, route reads to primary", lag);
+                        false
+                    }
+                    Err(e) => {
+                        tracing::warn!("db replica probe failed, route reads to primary: {:?}", e);
+                        false
+                    }
+                };
+                if flag.swap(healthy, Ordering::Relaxed) != healthy && healthy {
+                    tracing::info!("db replica recovered, route reads to replica");
+                }
+            }
+        });
+        health
+    }
+}
+
+async fn probe_lag(conn: &DatabaseConnection) -> Result<f64, DbErr> {
+    let row = conn
+        .query_one(Statement::from_string(DbBackend::Postgres, REPLICA_LAG_SQL))
+        .await?;
+    match row {
+        Some(r) => r.try_get::<f64>("", "lag"),
+        None => Ok(0.0),
+    }
+}
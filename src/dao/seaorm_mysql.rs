@@ -1,13 +1,29 @@
+use crate::{
+    config::reload::SharedSetting,
+    dao::{notify::StateBus, replica::ReplicaHealth},
+    error::DError,
+};
 #[allow(dead_code)]
 use redis::aio::MultiplexedConnection;
 use sea_orm::{Database, DatabaseConnection};
-use crate::{config::RuntimeSetting, error::DError};
 
 #[derive(Clone)]
 pub struct AppState {
-    pub rtx_setting: RuntimeSetting,
+    pub rtx_setting: SharedSetting,
     pub conn: DatabaseConnection,
     pub conn_r: Option<DatabaseConnection>,
     pub redis_pool: Option<MultiplexedConnection>,
-    
+    pub replica_health: ReplicaHealth,
+    pub state_bus: StateBus,
+}
+
+impl AppState {
+    ///
+    /// 读请求使用的连接: 副本可用时走副本, 否则回落主库
+    pub fn read_conn(&self) -> &DatabaseConnection {
+        match &self.conn_r {
+            Some(conn) if self.replica_health.is_healthy() => conn,
+            _ => &self.conn,
+        }
+    }
 }
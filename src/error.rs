@@ -6,10 +6,25 @@ use actix_web::{
 };
 use serde::Serialize;
 use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, Ordering};
 use thiserror::Error;
 
 pub type DResult = std::result::Result<HttpResponse, DError>;
 
+/// 数据库错误码
+pub const DB_ERR_CODE: i32 = 101;
+/// 未能归类的错误码
+pub const UNKNOWN_ERR_CODE: i32 = 9999;
+
+// 兼容旧客户端: 错误响应一律返回 200, 仅以 body 中的 code 区分
+static LEGACY_STATUS: AtomicBool = AtomicBool::new(false);
+
+///
+/// 启动时按配置设置错误响应的 http 状态码策略
+pub fn set_legacy_status(legacy: bool) {
+    LEGACY_STATUS.store(legacy, Ordering::Relaxed);
+}
+
 /// 自定义错误
 #[derive(Debug, Error)]
 pub enum LogicErr {
@@ -28,7 +43,21 @@ pub enum LogicErr {
     #[error("[NeedUpdate]need update!")]
     NeedUpdate(String),
     #[error("[Rpc]rpc call failed!{0}")]
-    RpcCallFailed(String)
+    RpcCallFailed(String),
+    #[error("[Unauthorized]{0}")]
+    Unauthorized(String),
+    #[error("[QueryTooLarge]{0}")]
+    QueryTooLarge(String),
+    #[error("[TooManyAliases]{0}")]
+    TooManyAliases(String),
+    #[error("[QueryTooComplex]{0}")]
+    QueryTooComplex(String),
+    #[error("[IllegalTransition]{0}")]
+    IllegalTransition(String),
+    #[error("[Forbidden]{0}")]
+    Forbidden(String),
+    #[error("[QueryTooDeep]{0}")]
+    QueryTooDeep(String),
 }
 
 impl LogicErr {
@@ -41,7 +70,34 @@ impl LogicErr {
             LogicErr::ConnectFailed(_) => 1005,
             LogicErr::ParamsError(_) => 1006,
             LogicErr::NeedUpdate(_) => 1007,
-            LogicErr::RpcCallFailed(_) => 1008
+            LogicErr::RpcCallFailed(_) => 1008,
+            LogicErr::Unauthorized(_) => 1009,
+            LogicErr::QueryTooLarge(_) => 1010,
+            LogicErr::TooManyAliases(_) => 1011,
+            LogicErr::QueryTooComplex(_) => 1012,
+            LogicErr::IllegalTransition(_) => 1013,
+            LogicErr::Forbidden(_) => 1014,
+            LogicErr::QueryTooDeep(_) => 1015,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            LogicErr::AlreadyExist(_) | LogicErr::IllegalTransition(_) => StatusCode::CONFLICT,
+            LogicErr::NotFound(_) => StatusCode::NOT_FOUND,
+            LogicErr::ParamsError(_)
+            | LogicErr::TooManyAliases(_)
+            | LogicErr::QueryTooComplex(_)
+            | LogicErr::QueryTooDeep(_) => StatusCode::BAD_REQUEST,
+            LogicErr::QueryTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            LogicErr::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            LogicErr::Forbidden(_) => StatusCode::FORBIDDEN,
+            LogicErr::NeedUpdate(_) => StatusCode::UPGRADE_REQUIRED,
+            LogicErr::RpcCallFailed(_) => StatusCode::BAD_GATEWAY,
+            LogicErr::ConnectFailed(_) => StatusCode::SERVICE_UNAVAILABLE,
+            LogicErr::InsertFailed(_) | LogicErr::UpdateFailed(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
         }
     }
 }
@@ -74,12 +130,31 @@ pub enum DError {
 impl DError {
     pub fn err_code(&self) -> i32 {
         match self {
-            DError::Db(_) => 101,
+            DError::Db(_) => DB_ERR_CODE,
             DError::SerializeError(_v) => 102,
             DError::RedisError(_) => 103,
             DError::Custom(v) => v.code(),
         }
     }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            DError::Custom(v) => v.status(),
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// graphql 错误附带 `extensions.code`(与 http 接口的 code 一致)
+///
+/// 解析函数中 `?` 走 async-graphql 的通用转换(保留原错误为 source), 统一在响应阶段补码;
+/// 需要在解析函数内直接构造时使用 `err.extend()`
+#[cfg(feature = "graphql")]
+impl seaography::async_graphql::ErrorExtensions for DError {
+    fn extend(&self) -> seaography::async_graphql::Error {
+        seaography::async_graphql::Error::new(self.to_string())
+            .extend_with(|_, e| e.set("code", self.err_code()))
+    }
 }
 
 /// http接口返回模型结构，提供基础的 code，msg，data 等json数据结构
@@ -90,13 +165,20 @@ pub struct RespErr {
 }
 
 impl ResponseError for DError {
+    fn status_code(&self) -> StatusCode {
+        if LEGACY_STATUS.load(Ordering::Relaxed) {
+            return StatusCode::OK;
+        }
+        self.status()
+    }
+
     fn error_response(&self) -> HttpResponse {
         let code: i32 = self.err_code();
         let error_response = RespErr {
             code: code,
             msg: self.to_string(),
         };
-        HttpResponse::Ok().json(error_response)
+        HttpResponse::build(self.status_code()).json(error_response)
     }
 }
 
@@ -0,0 +1,373 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+
+use super::{ClauseOp, MatchType, TagClause, TagCondition};
+
+/// 参与判定的玩家上下文
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerContext {
+    pub platform: Option<String>,
+    pub app_version: Option<String>,
+    pub country: Option<String>,
+    #[serde(default)]
+    pub attrs: Map<String, Value>,
+}
+
+impl PlayerContext {
+    ///
+    /// 取子句引用的字段, `attrs.a.b` 按层级读取自定义属性
+    pub fn lookup(&self, field: &str) -> Option<Value> {
+        let text = |v: &Option<String>| v.clone().map(Value::String);
+        match field {
+            "platform" => text(&self.platform),
+            "appVersion" => text(&self.app_version),
+            "country" => text(&self.country),
+            _ => {
+                let mut keys = field.strip_prefix("attrs.")?.split('.');
+                let mut cur = self.attrs.get(keys.next()?)?;
+                for key in keys {
+                    cur = cur.get(key)?;
+                }
+                (!cur.is_null()).then(|| cur.clone())
+            }
+        }
+    }
+}
+
+/// 单个子句的判定结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClauseResult {
+    pub field: String,
+    pub op: ClauseOp,
+    pub expected: Value,
+    pub actual: Option<Value>,
+    pub matched: bool,
+    // 未命中的原因
+    pub detail: Option<String>,
+}
+
+/// 整个条件的判定结果, 子句按配置顺序全部给出
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionResult {
+    pub match_type: MatchType,
+    pub matched: bool,
+    pub clauses: Vec<ClauseResult>,
+}
+
+impl TagCondition {
+    ///
+    /// 按上下文判定条件, 没有子句时视为命中
+    pub fn evaluate(&self, ctx: &PlayerContext) -> ConditionResult {
+        let clauses: Vec<ClauseResult> = self.clauses.iter().map(|c| c.evaluate(ctx)).collect();
+        let matched = clauses.is_empty()
+            || match self.match_type {
+                MatchType::All => clauses.iter().all(|c| c.matched),
+                MatchType::Any => clauses.iter().any(|c| c.matched),
+            };
+        ConditionResult {
+            match_type: self.match_type,
+            matched,
+            clauses,
+        }
+    }
+}
+
+impl TagClause {
+    pub fn evaluate(&self, ctx: &PlayerContext) -> ClauseResult {
+        let actual = ctx.lookup(&self.field);
+        let outcome = match (&actual, self.op) {
+            // value 为 false 时要求字段不存在
+            (_, ClauseOp::Exists) => {
+                let want = self.value.as_bool().unwrap_or(true);
+                expect(actual.is_some() == want, || {
+                    format!("exists is {}", actual.is_some())
+                })
+            }
+            (None, _) => Err("field missing".to_owned()),
+            (Some(actual), op) => compare(actual, op, &self.value),
+        };
+        ClauseResult {
+            field: self.field.clone(),
+            op: self.op,
+            expected: self.value.clone(),
+            matched: outcome.is_ok(),
+            detail: outcome.err(),
+            actual,
+        }
+    }
+}
+
+fn compare(actual: &Value, op: ClauseOp, expected: &Value) -> Result<(), String> {
+    match op {
+        ClauseOp::Eq => expect(loose_eq(actual, expected), || "not equal".to_owned()),
+        ClauseOp::Ne => expect(!loose_eq(actual, expected), || "equal".to_owned()),
+        ClauseOp::In | ClauseOp::NotIn => {
+            let list = expected
+                .as_array()
+                .ok_or_else(|| "value is not an array".to_owned())?;
+            let found = list.iter().any(|v| loose_eq(actual, v));
+            match op {
+                ClauseOp::In => expect(found, || "not in list".to_owned()),
+                _ => expect(!found, || "in list".to_owned()),
+            }
+        }
+        ClauseOp::Gt | ClauseOp::Gte | ClauseOp::Lt | ClauseOp::Lte => {
+            let (a, b) = (
+                as_f64(actual).ok_or_else(|| "field is not a number".to_owned())?,
+                as_f64(expected).ok_or_else(|| "value is not a number".to_owned())?,
+            );
+            let ord = a
+                .partial_cmp(&b)
+                .ok_or_else(|| "not comparable".to_owned())?;
+            ordered(ord, op)
+        }
+        ClauseOp::VersionGt | ClauseOp::VersionGte | ClauseOp::VersionLt | ClauseOp::VersionLte => {
+            let a = as_text(actual)
+                .as_deref()
+                .and_then(parse_version)
+                .ok_or_else(|| "field is not a version".to_owned())?;
+            let b = as_text(expected)
+                .as_deref()
+                .and_then(parse_version)
+                .ok_or_else(|| "value is not a version".to_owned())?;
+            ordered(cmp_version(&a, &b), op)
+        }
+        ClauseOp::Contains => {
+            let found = match actual {
+                Value::Array(items) => items.iter().any(|v| loose_eq(v, expected)),
+                _ => match (as_text(actual), as_text(expected)) {
+                    (Some(a), Some(b)) => a.contains(&b),
+                    _ => false,
+                },
+            };
+            expect(found, || "not contained".to_owned())
+        }
+        ClauseOp::Regex => {
+            let pattern = expected
+                .as_str()
+                .ok_or_else(|| "value is not a string".to_owned())?;
+            let re = Regex::new(pattern).map_err(|e| format!("invalid regex: {}", e))?;
+            let text = as_text(actual).ok_or_else(|| "field is not a string".to_owned())?;
+            expect(re.is_match(&text), || "regex not matched".to_owned())
+        }
+        ClauseOp::Exists => Ok(()),
+    }
+}
+
+fn ordered(ord: Ordering, op: ClauseOp) -> Result<(), String> {
+    let ok = match op {
+        ClauseOp::Gt | ClauseOp::VersionGt => ord == Ordering::Greater,
+        ClauseOp::Gte | ClauseOp::VersionGte => ord != Ordering::Less,
+        ClauseOp::Lt | ClauseOp::VersionLt => ord == Ordering::Less,
+        _ => ord != Ordering::Greater,
+    };
+    expect(ok, || format!("compare result is {:?}", ord))
+}
+
+fn expect(ok: bool, detail: impl FnOnce() -> String) -> Result<(), String> {
+    if ok {
+        Ok(())
+    } else {
+        Err(detail())
+    }
+}
+
+///
+/// 宽松相等: 数字与数字字符串视为相等, 字符串比较忽略大小写(平台/国家码写法不统一)
+fn loose_eq(a: &Value, b: &Value) -> bool {
+    if a == b {
+        return true;
+    }
+    match (a, b) {
+        (Value::Number(_), _) | (_, Value::Number(_)) => {
+            matches!((as_f64(a), as_f64(b)), (Some(x), Some(y)) if x == y)
+        }
+        (Value::String(x), Value::String(y)) => x.eq_ignore_ascii_case(y),
+        _ => false,
+    }
+}
+
+fn as_f64(v: &Value) -> Option<f64> {
+    match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_text(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+///
+/// `x.y.z` 版本号, 允许 `v` 前缀及 `-beta` 等后缀(忽略)
+fn parse_version(v: &str) -> Option<Vec<u64>> {
+    let v = v.trim().trim_start_matches(['v', 'V']);
+    let core = v.split(['-', '+']).next()?;
+    core.split('.').map(|p| p.parse().ok()).collect()
+}
+
+fn cmp_version(a: &[u64], b: &[u64]) -> Ordering {
+    let len = a.len().max(b.len());
+    (0..len)
+        .map(|i| a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0)))
+        .find(|o| *o != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ctx() -> PlayerContext {
+        PlayerContext {
+            platform: Some("GP".to_owned()),
+            app_version: Some("v1.10.0-beta".to_owned()),
+            country: Some("cn".to_owned()),
+            attrs: json!({"vip": "3", "device": {"ram": 4}})
+                .as_object()
+                .cloned()
+                .unwrap(),
+        }
+    }
+
+    fn clause(field: &str, op: ClauseOp, value: Value) -> TagClause {
+        TagClause {
+            field: field.to_owned(),
+            op,
+            value,
+        }
+    }
+
+    #[test]
+    fn lookup_reads_nested_attrs() {
+        let ctx = ctx();
+        assert_eq!(ctx.lookup("attrs.device.ram"), Some(json!(4)));
+        assert_eq!(ctx.lookup("attrs.device.cpu"), None);
+        assert_eq!(ctx.lookup("unknown"), None);
+    }
+
+    #[test]
+    fn empty_condition_matches() {
+        assert!(TagCondition::default().evaluate(&ctx()).matched);
+    }
+
+    #[test]
+    fn match_type_all_and_any() {
+        let clauses = vec![
+            clause("country", ClauseOp::Eq, json!("CN")),
+            clause("platform", ClauseOp::Eq, json!("IOS")),
+        ];
+        let all = TagCondition {
+            match_type: MatchType::All,
+            clauses: clauses.clone(),
+        };
+        let any = TagCondition {
+            match_type: MatchType::Any,
+            clauses,
+        };
+        let result = all.evaluate(&ctx());
+        assert!(!result.matched);
+        assert_eq!(result.clauses.len(), 2);
+        assert!(any.evaluate(&ctx()).matched);
+    }
+
+    #[test]
+    fn missing_field_and_exists() {
+        let ctx = ctx();
+        let missing = clause("attrs.level", ClauseOp::Gt, json!(1)).evaluate(&ctx);
+        assert!(!missing.matched);
+        assert_eq!(missing.detail.as_deref(), Some("field missing"));
+        assert!(
+            clause("attrs.level", ClauseOp::Exists, json!(false))
+                .evaluate(&ctx)
+                .matched
+        );
+        assert!(
+            clause("attrs.vip", ClauseOp::Exists, json!(true))
+                .evaluate(&ctx)
+                .matched
+        );
+    }
+
+    #[test]
+    fn numbers_compare_with_numeric_strings() {
+        let ctx = ctx();
+        assert!(
+            clause("attrs.vip", ClauseOp::Gte, json!(3))
+                .evaluate(&ctx)
+                .matched
+        );
+        assert!(
+            clause("attrs.vip", ClauseOp::In, json!([1, 3]))
+                .evaluate(&ctx)
+                .matched
+        );
+        let not_array = clause("attrs.vip", ClauseOp::In, json!(3)).evaluate(&ctx);
+        assert_eq!(not_array.detail.as_deref(), Some("value is not an array"));
+    }
+
+    #[test]
+    fn versions_ignore_prefix_suffix_and_missing_parts() {
+        let ctx = ctx();
+        assert!(
+            clause("appVersion", ClauseOp::VersionGt, json!("1.9"))
+                .evaluate(&ctx)
+                .matched
+        );
+        assert!(
+            clause("appVersion", ClauseOp::VersionLte, json!("1.10"))
+                .evaluate(&ctx)
+                .matched
+        );
+        assert!(
+            !clause("appVersion", ClauseOp::VersionGte, json!("x.y"))
+                .evaluate(&ctx)
+                .matched
+        );
+    }
+
+    #[test]
+    fn invalid_regex_does_not_match() {
+        let result = clause("country", ClauseOp::Regex, json!("(")).evaluate(&ctx());
+        assert!(!result.matched);
+        assert!(result.detail.unwrap().starts_with("invalid regex"));
+    }
+
+    #[test]
+    fn parse_accepts_clauses_and_field_maps() {
+        assert!(TagCondition::parse(None).unwrap().clauses.is_empty());
+        let map =
+            TagCondition::parse(Some(&json!({"platform": ["GP"], "app_version": "1.0"}))).unwrap();
+        assert_eq!(map.clauses.len(), 2);
+        assert!(map
+            .clauses
+            .iter()
+            .any(|c| c.field == "appVersion" && c.op == ClauseOp::Eq));
+        let clauses = json!({"clauses": [{"field": "country", "op": "eq", "value": "CN"}]});
+        assert!(
+            TagCondition::parse(Some(&clauses))
+                .unwrap()
+                .evaluate(&ctx())
+                .matched
+        );
+    }
+
+    #[test]
+    fn parse_rejects_unrecognized_formats() {
+        assert!(TagCondition::parse(Some(&json!([1, 2]))).is_none());
+        assert!(TagCondition::parse(Some(&json!({}))).is_none());
+        assert!(TagCondition::parse(Some(&json!({"level": 3}))).is_none());
+        assert!(TagCondition::parse(Some(&json!({"clauses": [{"op": "eq"}]}))).is_none());
+    }
+}
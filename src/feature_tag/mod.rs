@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+pub mod eval;
+pub mod service;
+
+/// `feature_tag_config.condition`: 对玩家上下文的一组判定条件
+///
+/// 库中现有 condition 的格式未有定义, 只判定以下两种可识别的写法, 其余见 [`TagCondition::parse`]:
+///
+/// ```json
+/// {
+///     "matchType": "all",
+///     "clauses": [
+///         { "field": "platform", "op": "in", "value": ["GP", "IOS"] },
+///         { "field": "appVersion", "op": "versionGte", "value": "1.2.0" },
+///         { "field": "attrs.vip", "op": "gte", "value": 3 }
+///     ]
+/// }
+/// ```
+///
+/// 或字段到期望值的映射, 数组为 in、其它为 eq, 全部满足时命中:
+///
+/// ```json
+/// { "platform": ["GP", "IOS"], "country": "CN" }
+/// ```
+///
+/// `field` 为 platform / appVersion / country, 或 `attrs.<key>` 引用自定义属性
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagCondition {
+    #[serde(default)]
+    pub match_type: MatchType,
+    #[serde(default)]
+    pub clauses: Vec<TagClause>,
+}
+
+/// 子句组合方式: 全部满足 / 任一满足
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchType {
+    #[default]
+    All,
+    Any,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagClause {
+    pub field: String,
+    pub op: ClauseOp,
+    pub value: Value,
+}
+
+impl TagCondition {
+    ///
+    /// 解析 condition 列, 为空视为无条件; 不是可识别的写法时返回 `None`(不参与判定, 而非判为无效)
+    pub fn parse(v: Option<&Value>) -> Option<TagCondition> {
+        let map = match v {
+            None | Some(Value::Null) => return Some(TagCondition::default()),
+            Some(Value::Object(map)) => map,
+            Some(_) => return None,
+        };
+        if map.contains_key("clauses") {
+            return serde_json::from_value(Value::Object(map.clone())).ok();
+        }
+        if map.is_empty() {
+            return None;
+        }
+        let clauses = map
+            .iter()
+            .map(|(field, value)| {
+                let field = match field.as_str() {
+                    "platform" | "country" => field.clone(),
+                    "appVersion" | "app_version" => "appVersion".to_owned(),
+                    f if f.starts_with("attrs.") => field.clone(),
+                    _ => return None,
+                };
+                let op = if value.is_array() {
+                    ClauseOp::In
+                } else {
+                    ClauseOp::Eq
+                };
+                Some(TagClause {
+                    field,
+                    op,
+                    value: value.clone(),
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(TagCondition {
+            match_type: MatchType::All,
+            clauses,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClauseOp {
+    Eq,
+    Ne,
+    In,
+    NotIn,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    // 按 x.y.z 版本号比较
+    VersionGt,
+    VersionGte,
+    VersionLt,
+    VersionLte,
+    Contains,
+    Regex,
+    Exists,
+}
@@ -0,0 +1,276 @@
+use entity_graphql::feature_config;
+use entity_graphql::feature_tag_config;
+use entity_graphql::sea_orm_active_enums::Efeatureplatform;
+use sea_orm::{
+    ActiveEnum, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::eval::{ConditionResult, PlayerContext};
+use super::TagCondition;
+use crate::error::{DError, LogicErr};
+use crate::services::graphql::custom_query::not_deleted;
+
+/// 单个标签的判定结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagResult {
+    pub id: i32,
+    pub platform: String,
+    pub new_name: Option<String>,
+    pub tag_desc: Option<String>,
+    // 原样给出, 取值含义未定义, 不参与过滤
+    pub state: Option<String>,
+    pub matched: bool,
+    // condition 不是可识别的写法时为空, 原因见 error
+    pub condition: Option<ConditionResult>,
+    pub error: Option<String>,
+}
+
+/// 命中标签下发的 feature
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedFeature {
+    pub feature_key: String,
+    pub feature_id: Option<i32>,
+    pub feature_param: Value,
+    // 来源标签, 多个标签下发同一 feature 时取 id 最小的标签
+    pub tag_id: i32,
+    // 同时下发该 feature 但被覆盖的标签
+    pub shadowed_tag_ids: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagEvaluation {
+    pub context: PlayerContext,
+    pub matched_tags: Vec<TagResult>,
+    pub unmatched_tags: Vec<TagResult>,
+    // condition 不是可识别写法的标签, 不参与判定
+    pub unsupported_tags: Vec<TagResult>,
+    pub features: Vec<ResolvedFeature>,
+    // feature_list 中引用但找不到对应 feature_config 的项
+    pub unresolved: Vec<Value>,
+}
+
+/// `feature_list` 中的一项: feature_config id、feature_key, 或自带参数的对象
+///
+/// ```json
+/// [12, "ui.new_lobby", {"featureKey": "shop.discount", "featureParam": {"rate": 0.8}}]
+/// ```
+enum FeatureRef {
+    Id(i32),
+    Key(String),
+    Inline(String, Value),
+}
+
+impl FeatureRef {
+    fn parse(v: &Value) -> Option<Self> {
+        match v {
+            // 超出 i32 的 id 不存在对应的 feature_config, 不截断以免指向其它记录
+            Value::Number(n) => n
+                .as_i64()
+                .and_then(|id| i32::try_from(id).ok())
+                .map(FeatureRef::Id),
+            Value::String(s) => match s.trim().parse::<i64>() {
+                Ok(id) => i32::try_from(id).ok().map(FeatureRef::Id),
+                Err(_) => Some(FeatureRef::Key(s.clone())),
+            },
+            Value::Object(m) => {
+                let key = m
+                    .get("featureKey")
+                    .or_else(|| m.get("feature_key"))?
+                    .as_str()?
+                    .to_owned();
+                match m.get("featureParam").or_else(|| m.get("feature_param")) {
+                    Some(param) => Some(FeatureRef::Inline(key, param.clone())),
+                    None => Some(FeatureRef::Key(key)),
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+///
+/// 上下文中的平台名转为枚举, 忽略大小写; android 对应 GP
+pub fn parse_platform(p: &str) -> Result<Efeatureplatform, DError> {
+    let mut name = p.trim().to_ascii_uppercase();
+    if name == "ANDROID" {
+        name = "GP".to_owned();
+    }
+    Efeatureplatform::try_from_value(&name)
+        .map_err(|_| DError::Custom(LogicErr::ParamsError(format!("platform <{}>", p))))
+}
+
+///
+/// 对玩家上下文判定全部未删除的 feature_tag_config(指定 platform 时仅判定该平台),
+/// 汇总命中标签下发的 feature 参数
+pub async fn evaluate(
+    db: &DatabaseConnection,
+    mut ctx: PlayerContext,
+) -> Result<TagEvaluation, DError> {
+    let platform = ctx.platform.as_deref().map(parse_platform).transpose()?;
+    // 子句按枚举值比较平台
+    if let Some(p) = &platform {
+        ctx.platform = Some(p.to_value());
+    }
+    let mut query = feature_tag_config::Entity::find()
+        .filter(not_deleted(feature_tag_config::Column::IsDelete))
+        .order_by_asc(feature_tag_config::Column::Id);
+    if let Some(platform) = &platform {
+        query = query.filter(feature_tag_config::Column::Platform.eq(platform.clone()));
+    }
+    let tags: Vec<feature_tag_config::Model> = query.all(db).await?;
+
+    let (mut matched_tags, mut unmatched_tags, mut unsupported_tags) = (vec![], vec![], vec![]);
+    let mut refs: Vec<(i32, Efeatureplatform, FeatureRef)> = vec![];
+    let mut unresolved = vec![];
+    for tag in tags {
+        let parsed = TagCondition::parse(tag.condition.as_ref());
+        let mut result = TagResult {
+            id: tag.id,
+            platform: tag.platform.to_value(),
+            new_name: tag.new_name,
+            tag_desc: tag.tag_desc,
+            state: tag.state,
+            matched: false,
+            condition: None,
+            error: None,
+        };
+        match parsed {
+            Some(cond) => {
+                let eval = cond.evaluate(&ctx);
+                result.matched = eval.matched;
+                result.condition = Some(eval);
+            }
+            None => {
+                result.error = Some("unsupported condition format".to_owned());
+                unsupported_tags.push(result);
+                continue;
+            }
+        }
+        if !result.matched {
+            unmatched_tags.push(result);
+            continue;
+        }
+        for item in tag.feature_list.as_array().into_iter().flatten() {
+            match FeatureRef::parse(item) {
+                Some(r) => refs.push((tag.id, tag.platform.clone(), r)),
+                None => unresolved.push(item.clone()),
+            }
+        }
+        matched_tags.push(result);
+    }
+
+    let (features, missing) = resolve_features(db, refs).await?;
+    unresolved.extend(missing);
+    Ok(TagEvaluation {
+        context: ctx,
+        matched_tags,
+        unmatched_tags,
+        unsupported_tags,
+        features,
+        unresolved,
+    })
+}
+
+///
+/// 按 id / feature_key(同平台) 加载 feature_param, 同一 feature_key 先到先得
+async fn resolve_features(
+    db: &DatabaseConnection,
+    refs: Vec<(i32, Efeatureplatform, FeatureRef)>,
+) -> Result<(Vec<ResolvedFeature>, Vec<Value>), DError> {
+    let ids: BTreeSet<i32> = refs
+        .iter()
+        .filter_map(|(_, _, r)| match r {
+            FeatureRef::Id(id) => Some(*id),
+            _ => None,
+        })
+        .collect();
+    let keys: BTreeSet<String> = refs
+        .iter()
+        .filter_map(|(_, _, r)| match r {
+            FeatureRef::Key(k) => Some(k.clone()),
+            _ => None,
+        })
+        .collect();
+    let rows: Vec<(i32, i32, String, Efeatureplatform, Value)> =
+        if ids.is_empty() && keys.is_empty() {
+            vec![]
+        } else {
+            feature_config::Entity::find()
+                .select_only()
+                .columns([
+                    feature_config::Column::Id,
+                    feature_config::Column::FeatureId,
+                    feature_config::Column::FeatureKey,
+                    feature_config::Column::Platform,
+                    feature_config::Column::FeatureParam,
+                ])
+                .filter(
+                    Condition::any()
+                        .add(feature_config::Column::Id.is_in(ids))
+                        .add(feature_config::Column::FeatureKey.is_in(keys)),
+                )
+                .filter(not_deleted(feature_config::Column::IsDelete))
+                .into_tuple()
+                .all(db)
+                .await?
+        };
+    let by_id: BTreeMap<i32, &(i32, i32, String, Efeatureplatform, Value)> =
+        rows.iter().map(|r| (r.0, r)).collect();
+
+    let mut resolved: BTreeMap<String, ResolvedFeature> = BTreeMap::new();
+    let mut order: Vec<String> = vec![];
+    let mut missing = vec![];
+    for (tag_id, platform, r) in refs {
+        let found = match r {
+            FeatureRef::Id(id) => by_id
+                .get(&id)
+                .map(|row| (row.2.clone(), Some(row.1), row.4.clone()))
+                .ok_or(Value::from(id)),
+            FeatureRef::Key(key) => rows
+                .iter()
+                .find(|row| row.2 == key && row.3 == platform)
+                .map(|row| (row.2.clone(), Some(row.1), row.4.clone()))
+                .ok_or(Value::from(key)),
+            FeatureRef::Inline(key, param) => Ok((key, None, param)),
+        };
+        let (feature_key, feature_id, feature_param) = match found {
+            Ok(f) => f,
+            Err(v) => {
+                missing.push(v);
+                continue;
+            }
+        };
+        match resolved.get_mut(&feature_key) {
+            Some(prev) => {
+                if prev.tag_id != tag_id && !prev.shadowed_tag_ids.contains(&tag_id) {
+                    prev.shadowed_tag_ids.push(tag_id);
+                }
+            }
+            None => {
+                order.push(feature_key.clone());
+                resolved.insert(
+                    feature_key.clone(),
+                    ResolvedFeature {
+                        feature_key,
+                        feature_id,
+                        feature_param,
+                        tag_id,
+                        shadowed_tag_ids: vec![],
+                    },
+                );
+            }
+        }
+    }
+    let features = order
+        .into_iter()
+        .filter_map(|k| resolved.remove(&k))
+        .collect();
+    Ok((features, missing))
+}
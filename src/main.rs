@@ -1,17 +1,26 @@
 #![allow(unused)]
+#[cfg(feature = "graphql")]
+mod approval;
 mod config;
 mod dao;
 pub mod error;
+#[cfg(feature = "graphql")]
+mod feature_tag;
 mod metrics;
 mod middleware;
+#[cfg(feature = "graphql")]
+mod polling;
 mod services;
 pub mod util;
+#[cfg(feature = "graphql")]
+mod workflow;
 use crate::{dao::init_sql_connection, error::DError};
-use actix_web::{web, App, HttpServer};
-use config::{log::LogConfig, RuntimeSetting};
-use dao::seaorm_mysql::AppState;
+use actix_web::{middleware::from_fn, web, App, HttpServer};
+use config::{log::LogConfig, reload::SharedSetting, source::ConfigSource, RuntimeSetting};
+use dao::{notify::StateBus, replica::ReplicaHealth, seaorm_mysql::AppState};
 use dotenv::dotenv;
 use log::LevelFilter;
+use middleware::auth::JwtVerifier;
 use mimalloc::MiMalloc;
 use redis::{aio::MultiplexedConnection, AsyncConnectionConfig, Client};
 use sea_orm::{ConnectOptions, Database};
@@ -27,31 +36,62 @@ use metrics::{RequestMetrics, RequestTracing};
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // ------------
-    // logger
-    let f = config::log::LogConfig::from_env().map_err(|e| {
-        tracing::error!("Failed to set up logger: {:?}", e);
-        std::io::Error::new(std::io::ErrorKind::Other, "Failed to load logger config")
+    // config: 环境变量 > .env > envs/<profile>.{toml,json} > envs/base.{toml,json}
+    let env_file = dotenv().ok();
+    let source = ConfigSource::load().map_err(|e| {
+        eprintln!("[Attention]Failed to load config files:\n{}", e);
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Failed to load config files",
+        )
+    })?;
+    // 日志尚未初始化, 配置错误直接输出到 stderr
+    let mut rt_setting: RuntimeSetting = RuntimeSetting::load(&source).map_err(|e| {
+        eprintln!("[Attention]Invalid config:\n{}", e);
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid config")
     })?;
-    LogConfig::init_logger(&f).map_err(|e| {
-        tracing::error!("Failed to init logger: {:?}", e);
+    // ------------
+
+    // ------------
+    // logger
+    let log_handle = LogConfig::init_logger(&rt_setting.log).map_err(|e| {
+        eprintln!("Failed to init logger: {:?}", e);
         std::io::Error::new(std::io::ErrorKind::Other, "Failed to init logger")
     })?;
-    let log_level = LevelFilter::from_str(&f.level.clone()).unwrap_or(LevelFilter::Info);
+    let log_level = LevelFilter::from_str(&rt_setting.log.level).unwrap_or(LevelFilter::Info);
     // ------------
 
     // ------------
     //*.env */
     tracing::info!("<AppState> connecting...");
-    let o = dotenv().ok().expect(
-        "[Attention]Not found .env config in root path!Can't execute server by default values!",
+    match &env_file {
+        Some(o) => tracing::info!("Load env file: {:?}", o),
+        None => tracing::warn!("Not found .env in root path, using config files and env vars"),
+    }
+    tracing::info!(
+        "Load profile <{}> config files: {:?}",
+        source.profile,
+        source.files
     );
-    tracing::info!("Load env file file: {:?}", o);
-    let rt_setting: RuntimeSetting = RuntimeSetting::default();
+    rt_setting.load_s3();
+    tracing::info!("---\n{:#}\n---", rt_setting.redacted());
+    error::set_legacy_status(rt_setting.base.legacy_status);
     // ------------
 
     // ------------
     // metrics
-    #[cfg(all(feature = "metrics", not(debug_assertions)))]
+    // prometheus 指标(/metrics), 需先于连接池/graphql 指标初始化
+    #[cfg(feature = "metrics")]
+    let metrics_handler = metrics::prometheus::gen_prometheus_handler(&rt_setting.base.svr_name)
+        .map_err(|e| {
+            tracing::error!("Failed to set up prometheus metrics: {:?}", e);
+            std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "Failed to set up prometheus metrics",
+            )
+        })?;
+    // otlp trace
+    #[cfg(feature = "metrics")]
     if let Some(metrics_config) = &rt_setting.metrics {
         metrics::prometheus::setup_metrics_tracing(&rt_setting.base.svr_name, metrics_config)
             .map_err(|e| {
@@ -73,7 +113,30 @@ async fn main() -> std::io::Result<()> {
     let state: AppState = {
         // main dao
         let db_main_connection = {
-            let con = init_sql_connection(&rt_setting.dao.db_main_addr, log_level)
+            let mut con = init_sql_connection(
+                &rt_setting.dao.db_main_addr,
+                &rt_setting.dao.db_pool,
+                log_level,
+            )
+            .await
+            .map_err(|e| {
+                std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    format!("Connect to sql failed! Err:{:?}", e),
+                )
+            })?;
+            #[cfg(feature = "metrics")]
+            metrics::db::record_db_queries("main", &mut con);
+            con
+        };
+        // replica dao
+        let db_replica_connection = {
+            if rt_setting.dao.db_replica_addr.len() > 0 {
+                let mut con = init_sql_connection(
+                    &rt_setting.dao.db_replica_addr,
+                    &rt_setting.dao.db_pool,
+                    log_level,
+                )
                 .await
                 .map_err(|e| {
                     std::io::Error::new(
@@ -81,39 +144,137 @@ async fn main() -> std::io::Result<()> {
                         format!("Connect to sql failed! Err:{:?}", e),
                     )
                 })?;
-            con
-        };
-        // replica dao
-        let db_replica_connection = {
-            if rt_setting.dao.db_replica_addr.len() > 0 {
-                let con = init_sql_connection(&rt_setting.dao.db_replica_addr, log_level)
-                    .await
-                    .map_err(|e| {
-                        std::io::Error::new(
-                            std::io::ErrorKind::Other,
-                            format!("Connect to sql failed! Err:{:?}", e),
-                        )
-                    })?;
+                #[cfg(feature = "metrics")]
+                metrics::db::record_db_queries("replica", &mut con);
                 Some(con)
             } else {
                 None
             }
         };
+        // 连接池饱和度指标
+        #[cfg(feature = "metrics")]
+        {
+            metrics::pool::observe_db_pool("main", &db_main_connection);
+            if let Some(con) = &db_replica_connection {
+                metrics::pool::observe_db_pool("replica", con);
+            }
+        }
+        // replica health
+        let replica_health = match &db_replica_connection {
+            Some(con) => ReplicaHealth::spawn_check(
+                con.clone(),
+                Duration::from_secs(rt_setting.dao.db_replica_max_lag),
+                Duration::from_secs(rt_setting.dao.db_replica_check_interval),
+            ),
+            None => ReplicaHealth::default(),
+        };
+        // 状态变更通知(graphql 订阅)
+        let state_bus = match &rt_setting.graphql.notify_channel {
+            Some(channel) => {
+                StateBus::spawn_listener(rt_setting.dao.db_main_addr.clone(), channel.clone())
+            }
+            None => StateBus::default(),
+        };
+        // redis
+        let redis_pool = match rt_setting.dao.redis_url() {
+            Some(url) => {
+                let pool = match Client::open(url) {
+                    Ok(client) => client
+                        .get_multiplexed_async_connection_with_config(&AsyncConnectionConfig::new())
+                        .await
+                        .map_err(DError::from),
+                    Err(e) => Err(DError::from(e)),
+                };
+                pool.map_err(|e| {
+                    tracing::error!("Connect to redis failed, cache disabled! Err:{:?}", e)
+                })
+                .ok()
+            }
+            None => {
+                tracing::warn!("REDIS_HOST is not set, graphql cache disabled!");
+                None
+            }
+        };
         AppState {
-            rtx_setting: rt_setting.to_owned(),
+            rtx_setting: SharedSetting::new(rt_setting.to_owned()),
             conn: db_main_connection,
             conn_r: db_replica_connection,
-            redis_pool: None,
+            redis_pool,
+            replica_health,
+            state_bus,
         }
     };
+    // auth
+    let jwt_verifier = web::Data::new(JwtVerifier::new(&rt_setting.auth).map_err(|e| {
+        tracing::error!("Failed to init jwt verifier: {:?}", e);
+        std::io::Error::new(std::io::ErrorKind::Other, "Failed to init jwt verifier")
+    })?);
+    // 按角色的访问策略, 启动时加载一次
+    let auth_policy = services::graphql::guard::AuthPolicy::from_setting(&rt_setting.auth)
+        .map(web::Data::new)
+        .map_err(|e| {
+            tracing::error!("Failed to load auth policy: {}", e);
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Failed to load auth policy",
+            )
+        })?;
+    // graphql schema: mutation 白名单 / 深度 / 复杂度限制来自 GraphqlSetting, 变更时重建
+    let schema = web::Data::new(
+        services::graphql::GqlSchema::spawn(
+            state.conn.clone(),
+            &state.rtx_setting,
+            auth_policy.clone().into_inner(),
+        )
+        .map_err(|e| {
+            tracing::error!("Failed to init graphql schema: {:?}", e);
+            std::io::Error::new(std::io::ErrorKind::Other, "Failed to init graphql schema")
+        })?,
+    );
+    tracing::info!("graphql schema init success");
+    tracing::info!("Visit GraphQL Playground at {:?}", rt_setting.base.host);
+    let sub_schema = web::Data::new(
+        services::graphql::subscription::schema(
+            state.state_bus.clone(),
+            auth_policy.clone().into_inner(),
+        )
+        .map_err(|e| {
+            tracing::error!("Failed to init graphql subscription schema: {:?}", e);
+            std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "Failed to init graphql subscription schema",
+            )
+        })?,
+    );
+    // 配置热更新: SIGHUP 或配置文件变更
+    config::reload::spawn_watcher(state.rtx_setting.clone(), source, log_handle);
+    // polling executor, 是否认领任务由 POLLING_ENABLE 控制(可热更新)
+    #[cfg(feature = "polling-executor")]
+    polling::executor::spawn(state.conn.clone(), state.rtx_setting.clone());
+    // 状态机未声明的已有状态只告警
+    {
+        let conn = state.conn.clone();
+        let machines = rt_setting.graphql.workflow_machines.clone();
+        tokio::spawn(async move {
+            if let Err(e) = workflow::service::check_stored_states(&conn, &machines).await {
+                tracing::warn!("[workflow] check stored states failed: {:?}", e);
+            }
+        });
+    }
+    // 外部 worker 写入的轮询日志按完成条件判定, 由 POLLING_EVALUATE_LOGS 控制(可热更新)
+    polling::evaluator::spawn(state.conn.clone(), state.rtx_setting.clone());
     // services
     let svr = HttpServer::new(move || {
-        let conn_graph = state.conn.clone();
-        let state_host = &state.rtx_setting.base.host.to_owned();
+        let schema = schema.clone();
+        let sub_schema = sub_schema.clone();
         let app = App::new()
+            .wrap(from_fn(middleware::auth::jwt_auth))
             .wrap(TracingLogger::default())
             .wrap(RequestMetrics::default())
             .wrap(RequestTracing::new())
+            .app_data(web::Data::new(state.clone()))
+            .app_data(jwt_verifier.clone())
+            .app_data(auth_policy.clone())
             .app_data(web::QueryConfig::default().error_handler(|req, _err| {
                 tracing::warn!("[error] on <GLOBAL> deserialize Query");
                 DError::Custom(error::LogicErr::ParamsError(req.to_string())).into()
@@ -124,28 +285,11 @@ async fn main() -> std::io::Result<()> {
             })) // All GraphQL
             .configure(move |c| {
                 use crate::services::graphql::{
-                    graphql_index, graphql_json, graphql_playground, GRAPHQL_BUILD_CTX,
+                    feature_tag_evaluate, graphql_cache_invalidate, graphql_index, graphql_json,
+                    graphql_playground, graphql_ws,
                 };
-                use actix_web::web::Data;
-                use entity_graphql;
-                // DEPTH_LIMIT
-                // COMPLEXITY_LIMIT
-                tracing::info!("graphql schema init success");
-                tracing::info!("Visit GraphQL Playground at {:?}", state_host);
-                let mut builder = seaography::Builder::new(&GRAPHQL_BUILD_CTX, conn_graph.clone());
-
-                // 注册 active enums（如果数据库有枚举类型）
-                // 如果数据库没有枚举，sea-orm-cli 不会生成 register_active_enums 函数
-                // 此时需要手动注释掉下面这行，或者在 entity_graphql/src/lib.rs 中添加空实现
-                // builder = entity_graphql::register_active_enums(builder);
-
-                builder = entity_graphql::register_entity_modules(builder);
-                let schema = builder
-                    .schema_builder()
-                    .data(conn_graph)
-                    .finish()
-                    .expect("graphql schema init failed");
-                c.app_data(Data::new(schema.clone()));
+                c.app_data(schema);
+                c.app_data(sub_schema);
                 c.service(
                     web::resource("/gql/health")
                         .guard(actix_web::guard::Get())
@@ -161,12 +305,30 @@ async fn main() -> std::io::Result<()> {
                         .guard(actix_web::guard::Get())
                         .to(graphql_playground),
                 );
+                c.service(
+                    web::resource("/gql/ws")
+                        .guard(actix_web::guard::Get())
+                        .to(graphql_ws),
+                );
                 c.service(
                     web::resource("/gql/json")
                         .guard(actix_web::guard::Post())
                         .to(graphql_json),
                 );
+                c.service(
+                    web::resource("/gql/cache/invalidate/{entity}")
+                        .guard(actix_web::guard::Post())
+                        .to(graphql_cache_invalidate),
+                );
+                c.service(
+                    web::resource("/gql/feature_tag/evaluate")
+                        .guard(actix_web::guard::Post())
+                        .to(feature_tag_evaluate),
+                );
             });
+        // prometheus 拉取
+        #[cfg(feature = "metrics")]
+        let app = app.route("/metrics", web::get().to(metrics_handler.clone()));
         app
     });
 
@@ -0,0 +1,54 @@
+use lazy_static::lazy_static;
+use opentelemetry::{global, KeyValue};
+use regex::Regex;
+use sea_orm::DatabaseConnection;
+
+lazy_static! {
+    // UPDATE "t" / FROM "t" / INTO "t", 允许 schema 前缀
+    static ref UPDATE_TARGET: Regex =
+        Regex::new(r#"(?i)^\s*UPDATE\s+(?:"?\w+"?\.)?"?(\w+)"?"#).unwrap();
+    static ref FROM_TARGET: Regex =
+        Regex::new(r#"(?i)\b(?:FROM|INTO)\s+(?:"?\w+"?\.)?"?(\w+)"?"#).unwrap();
+}
+
+///
+/// 记录 sql 耗时 `db.query.duration{db, entity, op, status}`(秒), entity 取语句操作的表名;
+/// 需在连接 clone 给其他组件之前调用
+pub fn record_db_queries(db: &'static str, conn: &mut DatabaseConnection) {
+    let duration = global::meter("db")
+        .f64_histogram("db.query.duration")
+        .with_unit("s")
+        .with_description("database statement duration by entity")
+        .build();
+    conn.set_metric_callback(move |info| {
+        let (op, entity) = statement_target(&info.statement.sql);
+        duration.record(
+            info.elapsed.as_secs_f64(),
+            &[
+                KeyValue::new("db", db),
+                KeyValue::new("entity", entity),
+                KeyValue::new("op", op),
+                KeyValue::new("status", if info.failed { "error" } else { "ok" }),
+            ],
+        );
+    });
+}
+
+fn statement_target(sql: &str) -> (String, String) {
+    let op = sql
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_uppercase();
+    let re: &Regex = if op == "UPDATE" {
+        &UPDATE_TARGET
+    } else {
+        &FROM_TARGET
+    };
+    let entity = re
+        .captures(sql)
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str().to_owned())
+        .unwrap_or_else(|| "unknown".to_owned());
+    (op, entity)
+}
@@ -0,0 +1,117 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use opentelemetry::{
+    global,
+    metrics::{Counter, Histogram},
+    KeyValue,
+};
+use seaography::async_graphql::{
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextExecute},
+    PathSegment, Response,
+};
+
+/// graphql 指标, 以 schema extension 注册:
+/// - `gql.operation.duration{operation, status}`: 每个操作的执行耗时(秒);
+///   operation 取自客户端传入的 operationName, 仅前 [`MAX_OPERATION_LABELS`] 个不同的名称单独成标签, 其余计入 `other`
+/// - `gql.resolver.errors{field}`: 解析出错次数, field 为去掉下标的错误路径, 如 `featureConfig.nodes.featureParam`
+pub struct GqlMetrics(Arc<Instruments>);
+
+/// operation 标签的上限, 避免任意 operationName 造成指标基数膨胀
+pub const MAX_OPERATION_LABELS: usize = 200;
+
+struct Instruments {
+    duration: Histogram<f64>,
+    errors: Counter<u64>,
+    operations: Mutex<HashSet<String>>,
+}
+
+impl Instruments {
+    ///
+    /// 已记录的名称或未达上限时使用原名, 否则归为 `other`
+    fn operation_label(&self, name: Option<&str>) -> String {
+        let Some(name) = name else {
+            return "anonymous".to_owned();
+        };
+        let mut known = self.operations.lock().unwrap_or_else(|e| e.into_inner());
+        if known.contains(name) {
+            return name.to_owned();
+        }
+        if known.len() < MAX_OPERATION_LABELS {
+            known.insert(name.to_owned());
+            return name.to_owned();
+        }
+        "other".to_owned()
+    }
+}
+
+impl GqlMetrics {
+    ///
+    /// 需在 meter provider 初始化之后调用
+    pub fn new() -> Self {
+        let meter = global::meter("graphql");
+        GqlMetrics(Arc::new(Instruments {
+            duration: meter
+                .f64_histogram("gql.operation.duration")
+                .with_unit("s")
+                .with_description("graphql operation execution duration")
+                .build(),
+            errors: meter
+                .u64_counter("gql.resolver.errors")
+                .with_description("graphql resolver errors by field path")
+                .build(),
+            operations: Mutex::new(HashSet::new()),
+        }))
+    }
+}
+
+impl ExtensionFactory for GqlMetrics {
+    fn create(&self) -> Arc<dyn Extension> {
+        self.0.clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl Extension for Instruments {
+    async fn execute(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        operation_name: Option<&str>,
+        next: NextExecute<'_>,
+    ) -> Response {
+        let start = Instant::now();
+        let resp = next.run(ctx, operation_name).await;
+        let status = if resp.is_ok() { "ok" } else { "error" };
+        // 与请求中任何操作都不匹配的名称会在执行前报错且不带 path
+        let matched = resp.is_ok() || resp.errors.iter().any(|e| !e.path.is_empty());
+        let operation = if matched {
+            self.operation_label(operation_name)
+        } else {
+            "other".to_owned()
+        };
+        self.duration.record(
+            start.elapsed().as_secs_f64(),
+            &[
+                KeyValue::new("operation", operation),
+                KeyValue::new("status", status),
+            ],
+        );
+        // 非空字段的错误会逐级冒泡, 按最终错误计数避免重复; 无 path 的是解析/校验错误, 不计入
+        for e in resp.errors.iter().filter(|e| !e.path.is_empty()) {
+            let field: Vec<&str> = e
+                .path
+                .iter()
+                .filter_map(|p| match p {
+                    PathSegment::Field(name) => Some(name.as_str()),
+                    PathSegment::Index(_) => None,
+                })
+                .collect();
+            self.errors
+                .add(1, &[KeyValue::new("field", field.join("."))]);
+        }
+        resp
+    }
+}
@@ -1,5 +1,9 @@
 pub mod prometheus;
 pub mod ali_cloud;
+pub mod pool;
+pub mod db;
+#[cfg(feature = "graphql")]
+pub mod graphql;
 
 #[allow(unused)]
 pub use actix_web_opentelemetry::{RequestMetrics, RequestTracing};
\ No newline at end of file
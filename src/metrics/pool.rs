@@ -0,0 +1,35 @@
+use opentelemetry::{global, KeyValue};
+use sea_orm::DatabaseConnection;
+
+///
+/// 注册连接池 gauge: `db.pool.connections{db, state=used|idle}` 与 `db.pool.max_connections{db}`,
+/// used 接近 max 即连接池饱和; 需在 meter provider 初始化之后调用
+pub fn observe_db_pool(db: &'static str, conn: &DatabaseConnection) {
+    let meter = global::meter("db.pool");
+    let pool = conn.get_postgres_connection_pool().clone();
+    meter
+        .u64_observable_gauge("db.pool.connections")
+        .with_description("database pool connections by state")
+        .with_callback(move |obs| {
+            let idle = pool.num_idle() as u64;
+            let used = (pool.size() as u64).saturating_sub(idle);
+            obs.observe(
+                used,
+                &[KeyValue::new("db", db), KeyValue::new("state", "used")],
+            );
+            obs.observe(
+                idle,
+                &[KeyValue::new("db", db), KeyValue::new("state", "idle")],
+            );
+        })
+        .build();
+    let max = conn
+        .get_postgres_connection_pool()
+        .options()
+        .get_max_connections() as u64;
+    meter
+        .u64_observable_gauge("db.pool.max_connections")
+        .with_description("database pool max connections")
+        .with_callback(move |obs| obs.observe(max, &[KeyValue::new("db", db)]))
+        .build();
+}
@@ -34,10 +34,19 @@ pub fn setup_metrics_tracing(
     Ok(())
 }
 
+/// 使用秒级分桶的直方图
+const SECONDS_HISTOGRAMS: &[&str] = &[
+    "http.server.duration",
+    "gql.operation.duration",
+    "db.query.duration",
+];
+
 ///
 /// 指标 prometheus接口响应
-#[allow(unused)]
-pub fn gen_prometheus_handler(service_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+/// 设置全局 meter provider, 返回挂载到 /metrics 的处理器; 需在创建任何指标之前调用
+pub fn gen_prometheus_handler(
+    service_name: &str,
+) -> Result<PrometheusMetricsHandler, Box<dyn std::error::Error>> {
     // prometheus
     let (metrics_handler, meter_provider) = {
         let registry = prometheus::Registry::new();
@@ -45,31 +54,30 @@ pub fn gen_prometheus_handler(service_name: &str) -> Result<(), Box<dyn std::err
             .with_registry(registry.clone())
             .build()?;
 
-        let provider = SdkMeterProvider::builder()
+        let mut builder = SdkMeterProvider::builder()
             .with_reader(exporter)
             .with_resource(
                 Resource::builder_empty()
                     .with_attribute(KeyValue::new("service.name", service_name.to_owned()))
                     .build(),
-            )
-            .with_view(
-                opentelemetry_sdk::metrics::new_view(
-                    Instrument::new().name("http.server.duration"),
-                    Stream::new().aggregation(Aggregation::ExplicitBucketHistogram {
-                        boundaries: vec![
-                            0.0, 0.005, 0.01, 0.025, 0.05, 0.075, 0.1, 0.25, 0.5, 0.75, 1.0, 2.5,
-                            5.0, 7.5, 10.0,
-                        ],
-                        record_min_max: true,
-                    }),
-                )
-                .unwrap(),
-            )
-            .build();
+            );
+        for name in SECONDS_HISTOGRAMS {
+            builder = builder.with_view(opentelemetry_sdk::metrics::new_view(
+                Instrument::new().name(*name),
+                Stream::new().aggregation(Aggregation::ExplicitBucketHistogram {
+                    boundaries: vec![
+                        0.0, 0.005, 0.01, 0.025, 0.05, 0.075, 0.1, 0.25, 0.5, 0.75, 1.0, 2.5, 5.0,
+                        7.5, 10.0,
+                    ],
+                    record_min_max: true,
+                }),
+            )?);
+        }
+        let provider = builder.build();
         global::set_meter_provider(provider.clone());
 
         (PrometheusMetricsHandler::new(registry), provider)
     };
     tracing::info!("init metrics done!");
-    Ok(())
+    Ok(metrics_handler)
 }
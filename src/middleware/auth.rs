@@ -0,0 +1,186 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header,
+    middleware::Next,
+    web, Error, HttpMessage,
+};
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{AlgorithmParameters, EllipticCurve, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::{
+    config::auth::AuthSetting,
+    error::{DError, LogicErr},
+};
+
+/// 鉴权通过后写入 request extensions 的身份信息, 同时作为 graphql 请求级 data
+#[derive(Debug, Clone, Default)]
+pub struct AuthClaims {
+    pub sub: Option<String>,
+    pub roles: Vec<String>,
+}
+
+impl AuthClaims {
+    pub fn has_any_role(&self, roles: &[String]) -> bool {
+        self.roles.iter().any(|r| roles.contains(r))
+    }
+}
+
+struct VerifyKey {
+    kid: Option<String>,
+    key: DecodingKey,
+    algorithms: Vec<Algorithm>,
+}
+
+/// jwt 校验器, 启动时根据 [`AuthSetting`] 加载共享密钥或 jwks
+pub struct JwtVerifier {
+    setting: AuthSetting,
+    keys: Vec<VerifyKey>,
+}
+
+impl JwtVerifier {
+    pub fn new(setting: &AuthSetting) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut keys = vec![];
+        if let Some(secret) = &setting.jwt_secret {
+            keys.push(VerifyKey {
+                kid: None,
+                key: DecodingKey::from_secret(secret.as_bytes()),
+                algorithms: vec![Algorithm::HS256, Algorithm::HS384, Algorithm::HS512],
+            });
+        }
+        if let Some(f) = &setting.jwks_file {
+            let js_cont: String = std::fs::read_to_string(f)?;
+            let jwks: JwkSet = serde_json::from_str(js_cont.as_str())?;
+            for jwk in jwks.keys.iter() {
+                let algorithms = match &jwk.algorithm {
+                    AlgorithmParameters::RSA(_) => vec![
+                        Algorithm::RS256,
+                        Algorithm::RS384,
+                        Algorithm::RS512,
+                        Algorithm::PS256,
+                        Algorithm::PS384,
+                        Algorithm::PS512,
+                    ],
+                    AlgorithmParameters::EllipticCurve(p) => match p.curve {
+                        EllipticCurve::P384 => vec![Algorithm::ES384],
+                        _ => vec![Algorithm::ES256],
+                    },
+                    AlgorithmParameters::OctetKeyPair(_) => vec![Algorithm::EdDSA],
+                    AlgorithmParameters::OctetKey(_) => {
+                        vec![Algorithm::HS256, Algorithm::HS384, Algorithm::HS512]
+                    }
+                };
+                keys.push(VerifyKey {
+                    kid: jwk.common.key_id.clone(),
+                    key: DecodingKey::from_jwk(jwk)?,
+                    algorithms,
+                });
+            }
+        }
+        if setting.enable && keys.is_empty() {
+            return Err(
+                "auth enabled but neither AUTH_JWT_SECRET nor AUTH_JWKS_FILE is set".into(),
+            );
+        }
+        tracing::info!(
+            "jwt auth enable: {}, {} verify keys loaded",
+            setting.enable,
+            keys.len()
+        );
+        Ok(JwtVerifier {
+            setting: setting.clone(),
+            keys,
+        })
+    }
+
+    fn is_public(&self, path: &str) -> bool {
+        self.setting.public_paths.iter().any(|p| p == path)
+    }
+
+    ///
+    /// 校验 token 签名、过期时间、iss/aud, 并提取角色
+    pub fn verify(&self, token: &str) -> Result<AuthClaims, DError> {
+        let unauthorized = |e: &dyn std::fmt::Display| {
+            DError::Custom(LogicErr::Unauthorized(format!("invalid token: {}", e)))
+        };
+        let head = decode_header(token).map_err(|e| unauthorized(&e))?;
+        let key = self
+            .keys
+            .iter()
+            .filter(|k| k.algorithms.contains(&head.alg))
+            .find(|k| head.kid.is_none() || k.kid.is_none() || k.kid == head.kid)
+            .ok_or_else(|| unauthorized(&"no matching verify key"))?;
+        let mut validation = Validation::new(head.alg);
+        if let Some(iss) = &self.setting.issuer {
+            validation.set_issuer(&[iss]);
+        }
+        match &self.setting.audience {
+            Some(aud) => validation.set_audience(&[aud]),
+            None => validation.validate_aud = false,
+        }
+        let data = decode::<Value>(token, &key.key, &validation).map_err(|e| unauthorized(&e))?;
+        let roles = match data.claims.get(&self.setting.role_claim) {
+            Some(Value::Array(arr)) => arr
+                .iter()
+                .filter_map(|v| v.as_str().map(|s| s.to_owned()))
+                .collect(),
+            Some(Value::String(s)) => s.split_whitespace().map(|s| s.to_owned()).collect(),
+            _ => vec![],
+        };
+        Ok(AuthClaims {
+            sub: data
+                .claims
+                .get("sub")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_owned()),
+            roles,
+        })
+    }
+}
+
+///
+/// bearer token 鉴权中间件, 通过后将 [`AuthClaims`] 写入 request extensions
+pub async fn jwt_auth(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let Some(verifier) = req.app_data::<web::Data<JwtVerifier>>().cloned() else {
+        return next.call(req).await;
+    };
+    if !verifier.setting.enable || verifier.is_public(req.path()) {
+        return next.call(req).await;
+    }
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_owned)
+        .or_else(|| ws_query_token(&req))
+        .ok_or_else(|| DError::Custom(LogicErr::Unauthorized("missing bearer token".to_owned())))?;
+    let claims = verifier.verify(token.trim())?;
+    tracing::debug!("[auth] sub={:?} roles={:?}", claims.sub, claims.roles);
+    req.extensions_mut().insert(claims);
+    next.call(req).await
+}
+
+///
+/// 浏览器无法为 websocket 握手设置请求头, 订阅连接允许通过 `?access_token=` 传递 token
+fn ws_query_token(req: &ServiceRequest) -> Option<String> {
+    let is_upgrade = req
+        .headers()
+        .get(header::UPGRADE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+    if !is_upgrade {
+        return None;
+    }
+    web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .ok()
+        .and_then(|q| q.into_inner().remove("access_token"))
+}
@@ -1 +1,2 @@
 //actix_middleware here
+pub mod auth;
@@ -0,0 +1,149 @@
+use entity_graphql::{st_polling_log, st_polling_task};
+use sea_orm::sea_query::{Expr, Query};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use std::collections::HashMap;
+
+use super::rule::{self, Evaluation};
+use super::TaskStatus;
+use crate::config::polling::{PollingSetting, TaskStatusCodes};
+use crate::config::reload::SharedSetting;
+use crate::error::DError;
+
+/// 每轮最多判定的日志条数
+const BATCH_SIZE: u64 = 500;
+
+///
+/// 启动日志判定任务: 外部 worker 执行的任务(asynq_task_id 非空)只写 st_polling_log,
+/// 按间隔读取新日志, 依任务的完成条件更新 `st_polling_task.status`; 不依赖内置执行器.
+///
+/// 只处理配置了 success_rule 的未终结任务, 其余任务的状态仍由 worker 维护;
+/// 启动后从头扫描这些任务的日志, 之后只读取新增日志. 开关为 POLLING_EVALUATE_LOGS(可热更新)
+pub fn spawn(conn: DatabaseConnection, shared: SharedSetting) {
+    tokio::spawn(async move {
+        let mut cursor = 0i64;
+        let mut enabled = false;
+        loop {
+            let setting = shared.load().polling.clone();
+            if setting.evaluate_logs != enabled {
+                enabled = setting.evaluate_logs;
+                tracing::info!(
+                    "[polling] log evaluator {}",
+                    if enabled { "started" } else { "paused" }
+                );
+            }
+            if enabled {
+                match evaluate_new_logs(&conn, &setting, cursor).await {
+                    Ok(last) => cursor = last,
+                    Err(e) => tracing::warn!("[polling] evaluate logs failed: {:?}", e),
+                }
+            }
+            tokio::time::sleep(setting.interval).await;
+        }
+    });
+}
+
+///
+/// 按 id 顺序判定 `cursor` 之后的日志, 返回新的游标
+async fn evaluate_new_logs(
+    conn: &DatabaseConnection,
+    setting: &PollingSetting,
+    mut cursor: i64,
+) -> Result<i64, DError> {
+    let codes = &setting.status_codes;
+    loop {
+        let pending = Query::select()
+            .column(st_polling_task::Column::Id)
+            .from(st_polling_task::Entity)
+            .and_where(st_polling_task::Column::DeletedAt.is_null())
+            .and_where(st_polling_task::Column::AsynqTaskId.is_not_null())
+            .and_where(st_polling_task::Column::SuccessRule.is_not_null())
+            .and_where(st_polling_task::Column::Status.is_not_in([
+                TaskStatus::Success.code(codes),
+                TaskStatus::Failed.code(codes),
+            ]))
+            .to_owned();
+        let logs = st_polling_log::Entity::find()
+            .filter(st_polling_log::Column::Id.gt(cursor))
+            .filter(st_polling_log::Column::TaskId.in_subquery(pending))
+            .order_by_asc(st_polling_log::Column::Id)
+            .limit(BATCH_SIZE)
+            .all(conn)
+            .await?;
+        let Some(last) = logs.last().map(|l| l.id) else {
+            return Ok(cursor);
+        };
+        let mut tasks: HashMap<i64, st_polling_task::Model> = st_polling_task::Entity::find()
+            .filter(st_polling_task::Column::Id.is_in(logs.iter().map(|l| l.task_id)))
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|t| (t.id, t))
+            .collect();
+        for log in logs.iter() {
+            // 同一批次中已判定为终态的任务, 忽略其后的日志
+            let Some(task) = tasks.get(&log.task_id) else {
+                continue;
+            };
+            let evaluation = rule::evaluate(task, log);
+            let status = evaluation.status;
+            update_task(conn, task.id, codes, &evaluation, log.response_body.clone()).await?;
+            tracing::debug!(
+                "[polling] task <{}> log <{}> -> {:?}",
+                task.id,
+                log.id,
+                status
+            );
+            if status.is_final() {
+                tasks.remove(&log.task_id);
+            }
+        }
+        cursor = last;
+        if (logs.len() as u64) < BATCH_SIZE {
+            return Ok(cursor);
+        }
+    }
+}
+
+///
+/// 写入判定结果; 已终结的任务不再修改
+pub async fn update_task(
+    conn: &DatabaseConnection,
+    task_id: i64,
+    codes: &TaskStatusCodes,
+    evaluation: &Evaluation,
+    last_response: Option<String>,
+) -> Result<(), DError> {
+    let status = evaluation.status;
+    let mut update = st_polling_task::Entity::update_many()
+        .col_expr(
+            st_polling_task::Column::Status,
+            Expr::value(status.code(codes)),
+        )
+        .col_expr(
+            st_polling_task::Column::LastResponse,
+            Expr::value(last_response),
+        )
+        .col_expr(
+            st_polling_task::Column::LastError,
+            Expr::value(evaluation.failure_summary()),
+        )
+        .col_expr(
+            st_polling_task::Column::UpdatedAt,
+            Expr::current_timestamp().into(),
+        );
+    if status.is_final() {
+        update = update.col_expr(
+            st_polling_task::Column::CompletedAt,
+            Expr::current_timestamp().into(),
+        );
+    }
+    update
+        .filter(st_polling_task::Column::Id.eq(task_id))
+        .filter(st_polling_task::Column::Status.is_not_in([
+            TaskStatus::Success.code(codes),
+            TaskStatus::Failed.code(codes),
+        ]))
+        .exec(conn)
+        .await?;
+    Ok(())
+}
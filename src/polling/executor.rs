@@ -0,0 +1,277 @@
+use actix_web::http::Method;
+use chrono::{DateTime, FixedOffset, Local};
+use entity_graphql::{st_polling_log, st_polling_task};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbBackend, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, Statement,
+};
+use serde_json::Value;
+use std::{cell::Cell, rc::Rc, time::Instant};
+
+use super::evaluator::update_task;
+use super::rule;
+use super::TaskStatus;
+use crate::config::polling::PollingSetting;
+use crate::config::reload::SharedSetting;
+use crate::error::DError;
+
+/// 认领待执行任务: 待执行, 或执行中/重试中但租约已过期(执行器异常退出)
+///
+/// asynq_task_id 非空的任务已投递给外部 worker, 不参与认领
+const CLAIM_SQL: &str = r#"
+UPDATE st_polling_task
+SET status = $1, started_at = COALESCE(started_at, now()), updated_at = now()
+WHERE id IN (
+    SELECT id FROM st_polling_task
+    WHERE deleted_at IS NULL
+        AND asynq_task_id IS NULL
+        AND protocol_type = $2
+        AND (
+            status = $3
+            OR (status IN ($1, $4) AND updated_at < now() - make_interval(secs => $5))
+        )
+    ORDER BY id
+    LIMIT $6
+    FOR UPDATE SKIP LOCKED
+)
+RETURNING *
+"#;
+
+/// 单次请求的执行结果, 对应一条 st_polling_log
+#[derive(Debug)]
+struct Attempt {
+    status: Option<u16>,
+    headers: Option<Value>,
+    body: Option<String>,
+    elapsed_ms: i64,
+    error: Option<String>,
+    executed_at: DateTime<FixedOffset>,
+}
+
+///
+/// 启动执行器: 按间隔认领任务并在 actix 运行时上并发执行(awc client 非 Send);
+/// 每轮读取当前配置, 开关/间隔/并发等热更新后下一轮生效, 已认领的任务按认领时的配置执行
+pub fn spawn(conn: DatabaseConnection, shared: SharedSetting) {
+    actix_web::rt::spawn(async move {
+        let client = awc::Client::default();
+        let inflight = Rc::new(Cell::new(0usize));
+        let mut interval = shared.load().polling.interval;
+        let mut ticker = actix_web::rt::time::interval(interval);
+        let mut enabled = false;
+        loop {
+            ticker.tick().await;
+            let setting = shared.load().polling.clone();
+            if setting.interval != interval {
+                interval = setting.interval;
+                ticker = actix_web::rt::time::interval(interval);
+            }
+            if setting.enable != enabled {
+                enabled = setting.enable;
+                tracing::info!(
+                    "[polling] executor {}, protocol_type={} concurrency={}",
+                    if enabled { "started" } else { "paused" },
+                    setting.protocol_type,
+                    setting.concurrency
+                );
+            }
+            if !enabled {
+                continue;
+            }
+            let free = setting.concurrency.saturating_sub(inflight.get());
+            if free == 0 {
+                continue;
+            }
+            let tasks = match claim(&conn, &setting, free).await {
+                Ok(tasks) => tasks,
+                Err(e) => {
+                    tracing::warn!("[polling] claim tasks failed: {:?}", e);
+                    continue;
+                }
+            };
+            for task in tasks {
+                inflight.set(inflight.get() + 1);
+                let (conn, client, setting, inflight) = (
+                    conn.clone(),
+                    client.clone(),
+                    setting.clone(),
+                    inflight.clone(),
+                );
+                actix_web::rt::spawn(async move {
+                    let task_id = task.id;
+                    if let Err(e) = run_task(&conn, &client, &setting, task).await {
+                        tracing::error!("[polling] task <{}> aborted: {:?}", task_id, e);
+                    }
+                    inflight.set(inflight.get() - 1);
+                });
+            }
+        }
+    });
+}
+
+async fn claim(
+    conn: &DatabaseConnection,
+    setting: &PollingSetting,
+    limit: usize,
+) -> Result<Vec<st_polling_task::Model>, DError> {
+    let codes = &setting.status_codes;
+    let tasks = st_polling_task::Entity::find()
+        .from_raw_sql(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            CLAIM_SQL,
+            [
+                TaskStatus::Running.code(codes).into(),
+                setting.protocol_type.into(),
+                TaskStatus::Pending.code(codes).into(),
+                TaskStatus::Retrying.code(codes).into(),
+                setting.lease.as_secs_f64().into(),
+                (limit as i64).into(),
+            ],
+        ))
+        .all(conn)
+        .await?;
+    Ok(tasks)
+}
+
+///
+/// 执行单个任务直到满足完成条件或判定失败, 每次请求写一条日志
+async fn run_task(
+    conn: &DatabaseConnection,
+    client: &awc::Client,
+    setting: &PollingSetting,
+    task: st_polling_task::Model,
+) -> Result<(), DError> {
+    // 重新认领的任务从已有日志之后继续计数
+    let mut retry = last_retry(conn, task.id).await?.map_or(0, |n| n + 1);
+    loop {
+        let attempt = request(client, setting, &task).await;
+        let log = write_log(conn, &task, retry, &attempt).await?;
+        let evaluation = rule::evaluate(&task, &log);
+        let status = evaluation.status;
+        let last_response = attempt.body.clone();
+        update_task(
+            conn,
+            task.id,
+            &setting.status_codes,
+            &evaluation,
+            last_response,
+        )
+        .await?;
+        tracing::debug!(
+            "[polling] task <{}> attempt {} -> {:?}",
+            task.id,
+            retry,
+            status
+        );
+        if status.is_final() {
+            return Ok(());
+        }
+        retry += 1;
+        actix_web::rt::time::sleep(setting.backoff(retry as u32)).await;
+    }
+}
+
+async fn last_retry(conn: &DatabaseConnection, task_id: i64) -> Result<Option<i64>, DError> {
+    let last = st_polling_log::Entity::find()
+        .select_only()
+        .column(st_polling_log::Column::RetryCount)
+        .filter(st_polling_log::Column::TaskId.eq(task_id))
+        .order_by_desc(st_polling_log::Column::RetryCount)
+        .into_tuple::<i64>()
+        .one(conn)
+        .await?;
+    Ok(last)
+}
+
+async fn request(
+    client: &awc::Client,
+    setting: &PollingSetting,
+    task: &st_polling_task::Model,
+) -> Attempt {
+    let executed_at = Local::now().fixed_offset();
+    let started = Instant::now();
+    let mut attempt = Attempt {
+        status: None,
+        headers: None,
+        body: None,
+        elapsed_ms: 0,
+        error: None,
+        executed_at,
+    };
+    let method = task.http_method.as_deref().unwrap_or("GET").trim();
+    let method = match Method::from_bytes(method.to_uppercase().as_bytes()) {
+        Ok(m) => m,
+        Err(_) => {
+            attempt.error = Some(format!("invalid http method <{}>", method));
+            return attempt;
+        }
+    };
+    let mut req = client
+        .request(method, task.endpoint.as_str())
+        .timeout(setting.request_timeout);
+    if let Some(Value::Object(headers)) = &task.headers {
+        for (k, v) in headers {
+            let v = match v {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            req = req.insert_header((k.as_str(), v));
+        }
+    }
+    let sent = match &task.request_body {
+        Some(body) => req.send_body(body.clone()).await,
+        None => req.send().await,
+    };
+    let mut resp = match sent {
+        Ok(resp) => resp,
+        Err(e) => {
+            attempt.elapsed_ms = started.elapsed().as_millis() as i64;
+            attempt.error = Some(e.to_string());
+            return attempt;
+        }
+    };
+    let status = resp.status();
+    attempt.status = Some(status.as_u16());
+    attempt.headers = Some(Value::Object(
+        resp.headers()
+            .iter()
+            .map(|(k, v)| {
+                let v = String::from_utf8_lossy(v.as_bytes()).into_owned();
+                (k.to_string(), Value::String(v))
+            })
+            .collect(),
+    ));
+    match resp.body().limit(setting.max_body_bytes).await {
+        Ok(body) => attempt.body = Some(String::from_utf8_lossy(&body).into_owned()),
+        Err(e) => attempt.error = Some(format!("read body failed: {}", e)),
+    }
+    attempt.elapsed_ms = started.elapsed().as_millis() as i64;
+    attempt
+}
+
+async fn write_log(
+    conn: &DatabaseConnection,
+    task: &st_polling_task::Model,
+    retry: i64,
+    attempt: &Attempt,
+) -> Result<st_polling_log::Model, DError> {
+    let log = st_polling_log::ActiveModel {
+        task_id: Set(task.id),
+        retry_count: Set(retry),
+        protocol_type: Set(i32::from(task.protocol_type)),
+        endpoint: Set(task.endpoint.clone()),
+        http_method: Set(task.http_method.clone()),
+        headers: Set(task.headers.clone()),
+        request_body: Set(task.request_body.clone()),
+        response_status: Set(attempt.status.map(i64::from)),
+        response_headers: Set(attempt.headers.clone()),
+        response_body: Set(attempt.body.clone()),
+        response_time: Set(Some(attempt.elapsed_ms)),
+        error_message: Set(attempt.error.clone()),
+        executed_at: Set(attempt.executed_at),
+        created_at: Set(Some(attempt.executed_at)),
+        ..Default::default()
+    }
+    .insert(conn)
+    .await?;
+    Ok(log)
+}
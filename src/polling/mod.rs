@@ -0,0 +1,34 @@
+pub mod evaluator;
+#[cfg(feature = "polling-executor")]
+pub mod executor;
+pub mod rule;
+
+use crate::config::polling::TaskStatusCodes;
+
+/// `st_polling_task.status` 的状态, 列中的取值见 [`TaskStatusCodes`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    Pending,
+    Running,
+    Success,
+    Failed,
+    Retrying,
+}
+
+impl TaskStatus {
+    pub fn code(self, codes: &TaskStatusCodes) -> i16 {
+        match self {
+            TaskStatus::Pending => codes.pending,
+            TaskStatus::Running => codes.running,
+            TaskStatus::Success => codes.success,
+            TaskStatus::Failed => codes.failed,
+            TaskStatus::Retrying => codes.retrying,
+        }
+    }
+
+    ///
+    /// 是否为终态(写入 completed_at, 不再认领)
+    pub fn is_final(self) -> bool {
+        matches!(self, TaskStatus::Success | TaskStatus::Failed)
+    }
+}
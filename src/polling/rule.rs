@@ -0,0 +1,368 @@
+use entity_graphql::{st_polling_log, st_polling_task};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+
+use super::TaskStatus;
+
+/// 轮询任务完成条件, 存于 `st_polling_task.success_rule`
+///
+/// ```json
+/// {
+///     "statusCodes": [200],
+///     "jsonPath": [{ "path": "$.data.state", "equals": "done" }, { "path": "$.data.url" }],
+///     "bodyRegex": ["\"code\":\\s*0"],
+///     "failRegex": ["\"state\":\\s*\"error\""],
+///     "timeoutSecs": 600
+/// }
+/// ```
+///
+/// 未配置时仅要求 2xx; `failRegex` 命中或超过 `timeoutSecs`(自 started_at 起)直接失败, 不再重试
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuccessRule {
+    #[serde(default)]
+    pub status_codes: Vec<u16>,
+    #[serde(default)]
+    pub json_path: Vec<JsonPathMatch>,
+    #[serde(default)]
+    pub body_regex: Vec<String>,
+    #[serde(default)]
+    pub fail_regex: Vec<String>,
+    pub timeout_secs: Option<i64>,
+}
+
+/// 响应体(json)中 `path` 命中的节点需存在, 配置 `equals` 时任一节点等于该值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonPathMatch {
+    pub path: String,
+    pub equals: Option<Value>,
+}
+
+/// 单条规则的检查结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleCheck {
+    pub rule: String,
+    pub passed: bool,
+    // 失败时终止任务, 不再重试
+    pub fatal: bool,
+    pub detail: String,
+}
+
+/// 单次请求(st_polling_log)的判定结果
+#[derive(Debug, Clone)]
+pub struct Evaluation {
+    pub status: TaskStatus,
+    pub checks: Vec<RuleCheck>,
+}
+
+impl Evaluation {
+    pub fn satisfied(&self) -> bool {
+        self.status == TaskStatus::Success
+    }
+
+    ///
+    /// 未通过的检查汇总, 写入 `last_error`
+    pub fn failure_summary(&self) -> Option<String> {
+        let failed: Vec<String> = self
+            .checks
+            .iter()
+            .filter(|c| !c.passed)
+            .map(|c| format!("{}: {}", c.rule, c.detail))
+            .collect();
+        (!failed.is_empty()).then(|| failed.join("; "))
+    }
+}
+
+impl RuleCheck {
+    fn new(rule: impl Into<String>, passed: bool, detail: impl Into<String>) -> Self {
+        RuleCheck {
+            rule: rule.into(),
+            passed,
+            fatal: false,
+            detail: detail.into(),
+        }
+    }
+
+    fn fatal(mut self) -> Self {
+        self.fatal = true;
+        self
+    }
+}
+
+///
+/// 按任务的完成条件判定一次请求结果: 全部通过为成功, 致命检查失败或重试用尽为失败, 否则重试
+pub fn evaluate(task: &st_polling_task::Model, log: &st_polling_log::Model) -> Evaluation {
+    let mut checks = match parse_rule(task) {
+        Ok(rule) => check_all(&rule, task, log),
+        Err(e) => vec![RuleCheck::new("successRule", false, e).fatal()],
+    };
+    // 请求失败或读取响应体失败, 即使有状态码也不算满足
+    if let Some(err) = &log.error_message {
+        checks.insert(0, RuleCheck::new("request", false, err.clone()));
+    }
+    let status = if checks.iter().all(|c| c.passed) {
+        TaskStatus::Success
+    } else if checks.iter().any(|c| !c.passed && c.fatal) {
+        TaskStatus::Failed
+    } else if log.retry_count >= i64::from(task.max_retry_count.max(0)) {
+        checks.push(
+            RuleCheck::new(
+                "maxRetryCount",
+                false,
+                format!("{} retries exhausted", task.max_retry_count),
+            )
+            .fatal(),
+        );
+        TaskStatus::Failed
+    } else {
+        TaskStatus::Retrying
+    };
+    Evaluation { status, checks }
+}
+
+fn parse_rule(task: &st_polling_task::Model) -> Result<SuccessRule, String> {
+    match &task.success_rule {
+        None | Some(Value::Null) => Ok(SuccessRule::default()),
+        Some(v) => serde_json::from_value(v.clone()).map_err(|e| format!("invalid rule: {}", e)),
+    }
+}
+
+fn check_all(
+    rule: &SuccessRule,
+    task: &st_polling_task::Model,
+    log: &st_polling_log::Model,
+) -> Vec<RuleCheck> {
+    let body = log.response_body.as_deref().unwrap_or_default();
+    let mut checks = vec![];
+    // 状态码
+    if let Some(status) = log.response_status {
+        let passed = if rule.status_codes.is_empty() {
+            (200..300).contains(&status)
+        } else {
+            rule.status_codes.iter().any(|c| i64::from(*c) == status)
+        };
+        let expect = if rule.status_codes.is_empty() {
+            "2xx".to_owned()
+        } else {
+            format!("{:?}", rule.status_codes)
+        };
+        checks.push(RuleCheck::new(
+            "statusCodes",
+            passed,
+            format!("got {}, expect {}", status, expect),
+        ));
+    }
+    // jsonpath
+    if !rule.json_path.is_empty() {
+        let doc = serde_json::from_str::<Value>(body);
+        for m in &rule.json_path {
+            let name = format!("jsonPath {}", m.path);
+            let check = match (&doc, JsonPath::parse(&m.path)) {
+                (_, Err(e)) => RuleCheck::new(name, false, format!("invalid path: {}", e)).fatal(),
+                (Err(e), _) => RuleCheck::new(name, false, format!("body is not json: {}", e)),
+                (Ok(doc), Ok(path)) => {
+                    let nodes = path.query(doc).all();
+                    match &m.equals {
+                        _ if nodes.is_empty() => RuleCheck::new(name, false, "no match"),
+                        None => {
+                            RuleCheck::new(name, true, format!("{} node(s) matched", nodes.len()))
+                        }
+                        Some(expect) => RuleCheck::new(
+                            name,
+                            nodes.iter().any(|n| *n == expect),
+                            format!("got {:?}, expect {}", nodes, expect),
+                        ),
+                    }
+                }
+            };
+            checks.push(check);
+        }
+    }
+    // 正则
+    for pattern in &rule.body_regex {
+        let name = format!("bodyRegex {}", pattern);
+        checks.push(match Regex::new(pattern) {
+            Ok(re) if re.is_match(body) => RuleCheck::new(name, true, "matched"),
+            Ok(_) => RuleCheck::new(name, false, "not matched"),
+            Err(e) => RuleCheck::new(name, false, format!("invalid regex: {}", e)).fatal(),
+        });
+    }
+    for pattern in &rule.fail_regex {
+        let name = format!("failRegex {}", pattern);
+        checks.push(match Regex::new(pattern) {
+            Ok(re) if re.is_match(body) => RuleCheck::new(name, false, "matched").fatal(),
+            Ok(_) => RuleCheck::new(name, true, "not matched"),
+            Err(e) => RuleCheck::new(name, false, format!("invalid regex: {}", e)).fatal(),
+        });
+    }
+    // 超时
+    if let (Some(timeout), Some(started_at)) = (rule.timeout_secs, task.started_at) {
+        let elapsed = (log.executed_at - started_at).num_seconds();
+        checks.push(
+            RuleCheck::new(
+                "timeoutSecs",
+                elapsed <= timeout,
+                format!("{}s elapsed, limit {}s", elapsed, timeout),
+            )
+            .fatal(),
+        );
+    }
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration};
+    use serde_json::json;
+
+    fn at(secs: i64) -> DateTime<chrono::FixedOffset> {
+        DateTime::parse_from_rfc3339("2024-01-01T00:00:00+08:00").unwrap() + Duration::seconds(secs)
+    }
+
+    fn task(rule: Option<Value>) -> st_polling_task::Model {
+        st_polling_task::Model {
+            id: 1,
+            task_name: "t".to_owned(),
+            task_key: "t".to_owned(),
+            protocol_type: 1,
+            endpoint: "http://127.0.0.1/".to_owned(),
+            http_method: None,
+            headers: None,
+            request_body: None,
+            status: 0,
+            max_retry_count: 3,
+            asynq_task_id: None,
+            last_response: None,
+            last_error: None,
+            started_at: Some(at(0)),
+            completed_at: None,
+            created_at: None,
+            updated_at: None,
+            created_avatar_name: None,
+            deleted_at: None,
+            success_rule: rule,
+        }
+    }
+
+    fn log(status: Option<i64>, body: &str) -> st_polling_log::Model {
+        st_polling_log::Model {
+            id: 1,
+            task_id: 1,
+            retry_count: 0,
+            protocol_type: 1,
+            endpoint: "http://127.0.0.1/".to_owned(),
+            http_method: None,
+            headers: None,
+            request_body: None,
+            response_status: status,
+            response_headers: None,
+            response_body: Some(body.to_owned()),
+            response_time: Some(10),
+            error_message: None,
+            executed_at: at(10),
+            created_at: None,
+            updated_at: None,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn default_rule_requires_2xx() {
+        assert_eq!(
+            evaluate(&task(None), &log(Some(204), "")).status,
+            TaskStatus::Success
+        );
+        let e = evaluate(&task(None), &log(Some(500), ""));
+        assert_eq!(e.status, TaskStatus::Retrying);
+        assert_eq!(
+            e.failure_summary().unwrap(),
+            "statusCodes: got 500, expect 2xx"
+        );
+    }
+
+    #[test]
+    fn request_error_fails_check_even_with_status() {
+        let mut l = log(Some(200), "");
+        l.error_message = Some("read body failed: overflow".to_owned());
+        let e = evaluate(&task(None), &l);
+        assert_eq!(e.status, TaskStatus::Retrying);
+        assert_eq!(e.checks[0].rule, "request");
+        assert!(!e.checks[0].passed);
+    }
+
+    #[test]
+    fn connection_error_without_status() {
+        let mut l = log(None, "");
+        l.error_message = Some("connect refused".to_owned());
+        let e = evaluate(&task(None), &l);
+        assert_eq!(e.status, TaskStatus::Retrying);
+        assert_eq!(e.checks.len(), 1);
+    }
+
+    #[test]
+    fn json_path_and_regex() {
+        let rule = json!({
+            "statusCodes": [200],
+            "jsonPath": [{"path": "$.data.state", "equals": "done"}, {"path": "$.data.url"}],
+            "bodyRegex": ["\"code\":\\s*0"]
+        });
+        let done = r#"{"code": 0, "data": {"state": "done", "url": "x"}}"#;
+        let running = r#"{"code": 0, "data": {"state": "running"}}"#;
+        assert_eq!(
+            evaluate(&task(Some(rule.clone())), &log(Some(200), done)).status,
+            TaskStatus::Success
+        );
+        let e = evaluate(&task(Some(rule.clone())), &log(Some(200), running));
+        assert_eq!(e.status, TaskStatus::Retrying);
+        assert_eq!(e.checks.iter().filter(|c| !c.passed).count(), 2);
+        // 非 json 响应体可重试
+        let e = evaluate(&task(Some(rule)), &log(Some(200), "<html>"));
+        assert_eq!(e.status, TaskStatus::Retrying);
+    }
+
+    #[test]
+    fn fail_regex_and_invalid_rules_are_fatal() {
+        let rule = json!({"failRegex": ["\"state\":\\s*\"error\""]});
+        let e = evaluate(&task(Some(rule)), &log(Some(200), r#"{"state": "error"}"#));
+        assert_eq!(e.status, TaskStatus::Failed);
+        let e = evaluate(
+            &task(Some(json!({"bodyRegex": ["("]}))),
+            &log(Some(200), ""),
+        );
+        assert_eq!(e.status, TaskStatus::Failed);
+        let e = evaluate(
+            &task(Some(json!({"statusCodes": "200"}))),
+            &log(Some(200), ""),
+        );
+        assert_eq!(e.status, TaskStatus::Failed);
+        assert_eq!(e.checks[0].rule, "successRule");
+    }
+
+    #[test]
+    fn timeout_is_fatal() {
+        let rule = json!({"timeoutSecs": 5});
+        let e = evaluate(&task(Some(rule.clone())), &log(Some(500), ""));
+        assert_eq!(e.status, TaskStatus::Failed);
+        // 未超时且满足
+        let mut l = log(Some(200), "");
+        l.executed_at = at(5);
+        assert_eq!(evaluate(&task(Some(rule)), &l).status, TaskStatus::Success);
+    }
+
+    #[test]
+    fn retries_exhausted() {
+        let mut l = log(Some(500), "");
+        l.retry_count = 3;
+        let e = evaluate(&task(None), &l);
+        assert_eq!(e.status, TaskStatus::Failed);
+        assert_eq!(e.checks.last().unwrap().rule, "maxRetryCount");
+        // 负数按 0 处理, 首次失败即终止
+        let mut t = task(None);
+        t.max_retry_count = -1;
+        assert_eq!(evaluate(&t, &log(Some(500), "")).status, TaskStatus::Failed);
+    }
+}
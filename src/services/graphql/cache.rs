@@ -0,0 +1,331 @@
+use redis::{aio::MultiplexedConnection, AsyncCommands};
+use seaography::async_graphql::parser::types::{
+    ExecutableDocument, OperationDefinition, OperationType,
+};
+use seaography::async_graphql::{Request, Response, Value};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+
+use super::custom_query::ROOT_FIELD_TABLES;
+use super::operation::{root_fields, walk_fields};
+use super::ENTITY_TABLES;
+use crate::config::cache::CacheSetting;
+use crate::error::DError;
+use crate::util::camel_to_snake;
+
+const CACHE_PREFIX: &str = "gql:cache";
+const INDEX_PREFIX: &str = "gql:cache:idx";
+
+/// 单次请求的缓存计划: 缓存key、涉及的实体与 TTL
+#[derive(Debug)]
+pub struct CachePlan {
+    pub key: String,
+    pub entities: Vec<String>,
+    pub ttl: u64,
+}
+
+impl CachePlan {
+    ///
+    /// 仅缓存 query 操作, 涉及的实体见 [`read_tables`]; 内省查询、未知根字段不缓存
+    pub fn from_request(
+        setting: &CacheSetting,
+        req: &Request,
+        doc: &ExecutableDocument,
+        op: &OperationDefinition,
+        scope: &str,
+    ) -> Option<Self> {
+        if !setting.enable || op.ty != OperationType::Query {
+            return None;
+        }
+        let entities = read_tables(doc, op)?;
+        let ttl = entities.iter().map(|e| setting.ttl_of(e)).min()?;
+        if ttl == 0 {
+            return None;
+        }
+        Some(CachePlan {
+            key: cache_key(req, scope),
+            entities,
+            ttl,
+        })
+    }
+
+    pub async fn get(&self, conn: &mut MultiplexedConnection) -> Option<Response> {
+        let cached: Option<String> = match conn.get(&self.key).await {
+            Ok(v) => v,
+            Err(e) => {
+                tracing::warn!("[gql cache] get <{}> failed: {:?}", self.key, e);
+                return None;
+            }
+        };
+        let data: Value = serde_json::from_str(&cached?).ok()?;
+        tracing::debug!("[gql cache] hit <{}>", self.key);
+        Some(Response::new(data))
+    }
+
+    ///
+    /// 写入缓存并登记到各实体的索引集合, 只缓存无错误的响应
+    pub async fn put(
+        &self,
+        conn: &mut MultiplexedConnection,
+        resp: &Response,
+        index_ttl: u64,
+    ) -> Result<(), DError> {
+        if !resp.errors.is_empty() {
+            return Ok(());
+        }
+        let data = serde_json::to_string(&resp.data)?;
+        let mut pipe = redis::pipe();
+        pipe.atomic().set_ex(&self.key, data, self.ttl).ignore();
+        for entity in self.entities.iter() {
+            let idx = index_key(entity);
+            pipe.sadd(&idx, &self.key)
+                .ignore()
+                .expire(&idx, index_ttl.max(self.ttl) as i64)
+                .ignore();
+        }
+        pipe.query_async::<()>(conn).await?;
+        Ok(())
+    }
+}
+
+///
+/// 按实体清空缓存, 返回删除的缓存条数
+pub async fn invalidate_entity(
+    conn: &mut MultiplexedConnection,
+    entity: &str,
+) -> Result<usize, DError> {
+    let idx = index_key(entity);
+    let keys: Vec<String> = conn.smembers(&idx).await?;
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    if !keys.is_empty() {
+        pipe.del(&keys).ignore();
+    }
+    pipe.del(&idx).ignore();
+    pipe.query_async::<()>(conn).await?;
+    Ok(keys.len())
+}
+
+fn index_key(entity: &str) -> String {
+    format!("{}:{}", INDEX_PREFIX, entity)
+}
+
+/// seaography 生成的实体 mutation 字段后缀
+const MUTATION_SUFFIXES: &[&str] = &["CreateOne", "CreateBatch", "Update", "Delete"];
+
+fn entity_table(field: &str) -> Option<&'static str> {
+    let name = camel_to_snake(field);
+    ENTITY_TABLES.iter().copied().find(|t| *t == name)
+}
+
+fn custom_tables(field: &str) -> Option<&'static [&'static str]> {
+    ROOT_FIELD_TABLES
+        .iter()
+        .find(|(name, _)| *name == field)
+        .map(|(_, tables)| *tables)
+}
+
+///
+/// 查询读取的表: 实体根字段及任意层级的关联字段(如 `solution { solutionLabel }`)取实体表,
+/// 自定义根字段取其登记的表; 含内省或未知根字段时返回 None
+fn read_tables(doc: &ExecutableDocument, op: &OperationDefinition) -> Option<Vec<String>> {
+    let mut tables = BTreeSet::new();
+    let mut cacheable = true;
+    walk_fields(doc, &op.selection_set.node, &mut |name, depth| {
+        if name == "__typename" {
+            return;
+        }
+        if name.starts_with("__") {
+            cacheable = false;
+            return;
+        }
+        if depth == 0 {
+            if let Some(custom) = custom_tables(name) {
+                tables.extend(custom.iter().copied());
+                return;
+            }
+        }
+        match entity_table(name) {
+            Some(table) => {
+                tables.insert(table);
+            }
+            None if depth == 0 => cacheable = false,
+            None => {}
+        }
+    });
+    cacheable.then(|| tables.into_iter().map(str::to_owned).collect())
+}
+
+///
+/// mutation 写入的表, 执行后清空这些表的缓存; 自定义变更取其登记的表
+pub fn mutated_tables(doc: &ExecutableDocument, op: &OperationDefinition) -> Vec<String> {
+    let mut tables = BTreeSet::new();
+    for name in root_fields(doc, op) {
+        if let Some(custom) = custom_tables(name) {
+            tables.extend(custom.iter().copied());
+            continue;
+        }
+        let entity = MUTATION_SUFFIXES
+            .iter()
+            .find_map(|s| name.strip_suffix(s))
+            .and_then(entity_table);
+        tables.extend(entity);
+    }
+    tables.into_iter().map(str::to_owned).collect()
+}
+
+///
+/// 缓存key: 身份范围 + 规范化后的 query + 排序后的 variables + operation name 的 SHA-256
+fn cache_key(req: &Request, scope: &str) -> String {
+    let variables = canonical_json(
+        &req.variables
+            .clone()
+            .into_value()
+            .into_json()
+            .unwrap_or_default(),
+    );
+    let mut hasher = Sha256::new();
+    for part in [
+        scope,
+        &normalize_query(&req.query),
+        &variables,
+        req.operation_name.as_deref().unwrap_or_default(),
+    ] {
+        // 带长度前缀, 避免不同分段拼接出相同输入
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part.as_bytes());
+    }
+    format!("{}:{:x}", CACHE_PREFIX, hasher.finalize())
+}
+
+///
+/// 去掉注释、逗号与多余空白; 字符串字面量保持原样
+fn normalize_query(query: &str) -> String {
+    const PUNCTUATORS: &[char] = &[
+        '{', '}', '(', ')', '[', ']', ':', '=', '!', '$', '@', '|', '&',
+    ];
+    let mut out = String::with_capacity(query.len());
+    let mut chars = query.chars().peekable();
+    let mut pending_space = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                if pending_space && !out.ends_with(PUNCTUATORS) {
+                    out.push(' ');
+                }
+                pending_space = false;
+                out.push(c);
+                let mut escaped = false;
+                for s in chars.by_ref() {
+                    out.push(s);
+                    match s {
+                        '\\' if !escaped => escaped = true,
+                        '"' if !escaped => break,
+                        _ => escaped = false,
+                    }
+                }
+            }
+            '#' => {
+                for s in chars.by_ref() {
+                    if s == '\n' {
+                        break;
+                    }
+                }
+                pending_space = true;
+            }
+            c if c.is_whitespace() || c == ',' => pending_space = true,
+            c => {
+                if pending_space
+                    && !out.is_empty()
+                    && !out.ends_with(PUNCTUATORS)
+                    && !PUNCTUATORS.contains(&c)
+                {
+                    out.push(' ');
+                }
+                pending_space = false;
+                out.push(c);
+            }
+        }
+    }
+    out
+}
+
+///
+/// 对象key排序后的json字符串, 保证变量顺序不同的请求命中同一缓存
+fn canonical_json(v: &serde_json::Value) -> String {
+    match v {
+        serde_json::Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let body: Vec<String> = keys
+                .into_iter()
+                .map(|k| format!("{:?}:{}", k, canonical_json(&map[k])))
+                .collect();
+            format!("{{{}}}", body.join(","))
+        }
+        serde_json::Value::Array(arr) => {
+            let body: Vec<String> = arr.iter().map(canonical_json).collect();
+            format!("[{}]", body.join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn normalize_query_drops_insignificant_tokens() {
+        let a = "query Q($id: Int!) {\n  solution(filters: {id: {eq: $id}}) {\n    nodes { id, name } # comment\n  }\n}";
+        let b = "query Q($id:Int!){solution(filters:{id:{eq:$id}}){nodes{id name}}}";
+        assert_eq!(normalize_query(a), b);
+        assert_eq!(normalize_query(b), b);
+    }
+
+    #[test]
+    fn normalize_query_keeps_string_literals() {
+        assert_eq!(
+            normalize_query(r#"{ a(s: "x,  # y") }"#),
+            r#"{a(s:"x,  # y")}"#
+        );
+        // 转义引号不结束字符串
+        assert_eq!(
+            normalize_query(r#"{ a(s: "x\" ,  y") }"#),
+            r#"{a(s:"x\" ,  y")}"#
+        );
+        assert_eq!(
+            normalize_query(r#"{ a(s:"\\" , t: 1) }"#),
+            r#"{a(s:"\\" t:1)}"#
+        );
+    }
+
+    #[test]
+    fn normalize_query_separates_names() {
+        assert_eq!(normalize_query("{ a b\n\tc }"), "{a b c}");
+        assert_eq!(normalize_query("  {a}  "), "{a}");
+        assert_eq!(normalize_query(""), "");
+    }
+
+    #[test]
+    fn canonical_json_sorts_keys_recursively() {
+        let a = json!({"b": 1, "a": {"d": [ {"y": 1, "x": 2} ], "c": null}});
+        let b = json!({"a": {"c": null, "d": [ {"x": 2, "y": 1} ]}, "b": 1});
+        assert_eq!(canonical_json(&a), canonical_json(&b));
+        assert_eq!(
+            canonical_json(&a),
+            r#"{"a":{"c":null,"d":[{"x":2,"y":1}]},"b":1}"#
+        );
+    }
+
+    #[test]
+    fn canonical_json_keeps_array_order_and_escapes() {
+        assert_ne!(
+            canonical_json(&json!([1, 2])),
+            canonical_json(&json!([2, 1]))
+        );
+        assert_eq!(canonical_json(&json!({"k\"": "v\n"})), r#"{"k\"":"v\n"}"#);
+        assert_eq!(canonical_json(&json!({})), "{}");
+    }
+}
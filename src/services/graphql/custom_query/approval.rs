@@ -0,0 +1,68 @@
+use sea_orm::DatabaseConnection;
+use seaography::async_graphql::dynamic::{Field, FieldFuture, InputValue, TypeRef};
+use seaography::Builder;
+
+use super::{json_object, json_output, JsonField};
+use crate::approval::service::{self, ApprovalTable};
+use crate::approval::{ApprovalRecord, Decision};
+use crate::error::{DError, LogicErr};
+use crate::middleware::auth::AuthClaims;
+
+pub fn register(builder: &mut Builder) {
+    builder.outputs.push(json_object(
+        "ApprovalRecord",
+        vec![
+            JsonField::Scalar("role", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("approver", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("decision", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("comment", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("at", TypeRef::named_nn(TypeRef::STRING)),
+        ],
+    ));
+    builder.outputs.push(json_object(
+        "ApprovalState",
+        vec![
+            JsonField::Scalar("flow", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("id", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("approvalType", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("outcome", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("isComplete", TypeRef::named_nn(TypeRef::BOOLEAN)),
+            JsonField::Object("records", TypeRef::named_nn_list_nn("ApprovalRecord")),
+            JsonField::Scalar("pendingRoles", TypeRef::named_nn_list_nn(TypeRef::STRING)),
+        ],
+    ));
+    builder
+        .mutations
+        .push(decision_field("approve", Decision::Approve));
+    builder
+        .mutations
+        .push(decision_field("reject", Decision::Reject));
+}
+
+///
+/// 以当前 jwt 身份审批 st_wf_approval_flow / fc_cfg_approval_flow, `flow` 为表名
+fn decision_field(name: &str, decision: Decision) -> Field {
+    Field::new(name, TypeRef::named_nn("ApprovalState"), move |ctx| {
+        FieldFuture::new(async move {
+            let db = ctx.data::<DatabaseConnection>()?;
+            let claims = ctx.data_opt::<AuthClaims>().ok_or_else(|| {
+                DError::Custom(LogicErr::Unauthorized(
+                    "approver identity required".to_owned(),
+                ))
+            })?;
+            let flow = ctx.args.try_get("flow")?.string()?;
+            let table = ApprovalTable::from_name(flow)
+                .ok_or_else(|| DError::Custom(LogicErr::ParamsError(format!("flow <{}>", flow))))?;
+            let id = ctx.args.try_get("id")?.i64()? as i32;
+            let comment = match ctx.args.get("comment") {
+                Some(v) => Some(v.string()?.to_owned()),
+                None => None,
+            };
+            let state = service::decide(db, table, id, claims, decision, comment).await?;
+            json_output(&state)
+        })
+    })
+    .argument(InputValue::new("flow", TypeRef::named_nn(TypeRef::STRING)))
+    .argument(InputValue::new("id", TypeRef::named_nn(TypeRef::INT)))
+    .argument(InputValue::new("comment", TypeRef::named(TypeRef::STRING)))
+}
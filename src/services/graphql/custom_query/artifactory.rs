@@ -0,0 +1,68 @@
+use entity_graphql::artifactory;
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter};
+use seaography::async_graphql::dynamic::{Field, FieldFuture, FieldValue, InputValue, TypeRef};
+use seaography::Builder;
+
+use crate::error::{DError, LogicErr};
+use crate::util::convert_version_to_int32;
+
+pub fn register(builder: &mut Builder) {
+    builder.queries.push(latest_compatible_artifactory());
+}
+
+///
+/// 查询与指定 runtime 版本兼容的最新可用制品
+///
+/// 兼容条件: 同一 runtime、`is_artifactory_ready`、版本落在 `[min_runtime_ver, max_runtime_ver]`
+/// (未设置的边界视为不限制)；多个候选时取制品版本最高者, 同版本取最新创建的
+fn latest_compatible_artifactory() -> Field {
+    Field::new(
+        "latestCompatibleArtifactory",
+        TypeRef::named("Artifactory"),
+        |ctx| {
+            FieldFuture::new(async move {
+                let db = ctx.data::<DatabaseConnection>()?;
+                let runtime = ctx.args.try_get("runtime")?.string()?.to_owned();
+                let runtime_version = ctx.args.try_get("runtimeVersion")?.string()?;
+                let ver_int = convert_version_to_int32(runtime_version).map_err(|e| {
+                    DError::Custom(LogicErr::ParamsError(format!(
+                        "runtimeVersion <{}>: {}",
+                        runtime_version, e
+                    )))
+                })?;
+                let candidates = artifactory::Entity::find()
+                    .filter(artifactory::Column::Runtime.eq(runtime))
+                    .filter(artifactory::Column::IsArtifactoryReady.eq(true))
+                    .filter(
+                        Condition::any()
+                            .add(artifactory::Column::MinRuntimeVer.is_null())
+                            .add(artifactory::Column::MinRuntimeVer.lte(ver_int)),
+                    )
+                    .filter(
+                        Condition::any()
+                            .add(artifactory::Column::MaxRuntimeVer.is_null())
+                            .add(artifactory::Column::MaxRuntimeVer.gte(ver_int)),
+                    )
+                    .all(db)
+                    .await
+                    .map_err(DError::from)?;
+                let newest = candidates.into_iter().max_by_key(|m| {
+                    (
+                        convert_version_to_int32(&m.ver).unwrap_or(-1),
+                        m.create_time,
+                        m.pid,
+                    )
+                });
+                Ok(newest.map(FieldValue::owned_any))
+            })
+        },
+    )
+    .argument(InputValue::new(
+        "runtime",
+        TypeRef::named_nn(TypeRef::STRING),
+    ))
+    .argument(InputValue::new(
+        "runtimeVersion",
+        TypeRef::named_nn(TypeRef::STRING),
+    ))
+}
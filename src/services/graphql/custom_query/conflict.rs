@@ -0,0 +1,243 @@
+use entity_graphql::feature_config;
+use entity_graphql::feature_config_conflict;
+use entity_graphql::sea_orm_active_enums::{Econflicttype, Efeatureplatform};
+use sea_orm::{
+    ActiveEnum, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter, QuerySelect,
+};
+use seaography::async_graphql::dynamic::{Field, FieldFuture, InputValue, TypeRef};
+use seaography::Builder;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::{json_object, json_output, JsonField};
+use crate::error::DError;
+use crate::feature_tag::service::parse_platform;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConflictPair {
+    id: i32,
+    id_base: i32,
+    id_conflict: i32,
+    conflict_type: String,
+    comment: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConflictAnalysis {
+    pairs: Vec<ConflictPair>,
+    // 通过冲突关系传递连通的配置分组(仅包含2个及以上配置的组)
+    conflict_groups: Vec<Vec<i32>>,
+    // 在该平台下不存在或已删除的配置id
+    missing_ids: Vec<i32>,
+    // 集合内不存在冲突且配置均存在时可一起发布
+    shippable: bool,
+}
+
+pub fn register(builder: &mut Builder) {
+    builder.outputs.push(json_object(
+        "FeatureConflictPair",
+        vec![
+            JsonField::Scalar("id", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("idBase", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("idConflict", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("conflictType", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("comment", TypeRef::named(TypeRef::STRING)),
+        ],
+    ));
+    builder.outputs.push(json_object(
+        "FeatureConflictAnalysis",
+        vec![
+            JsonField::Object("pairs", TypeRef::named_nn_list_nn("FeatureConflictPair")),
+            JsonField::Scalar(
+                "conflictGroups",
+                TypeRef::NonNull(Box::new(TypeRef::List(Box::new(
+                    TypeRef::named_nn_list_nn(TypeRef::INT),
+                )))),
+            ),
+            JsonField::Scalar("missingIds", TypeRef::named_nn_list_nn(TypeRef::INT)),
+            JsonField::Scalar("shippable", TypeRef::named_nn(TypeRef::BOOLEAN)),
+        ],
+    ));
+    builder.queries.push(feature_conflict_analysis());
+}
+
+///
+/// 分析一组 feature_config 在指定平台下的两两冲突/兼容关系、传递冲突分组以及能否一起发布
+fn feature_conflict_analysis() -> Field {
+    Field::new(
+        "featureConflictAnalysis",
+        TypeRef::named_nn("FeatureConflictAnalysis"),
+        |ctx| {
+            FieldFuture::new(async move {
+                let db = ctx.data::<DatabaseConnection>()?;
+                let ids = ctx
+                    .args
+                    .try_get("ids")?
+                    .list()?
+                    .iter()
+                    .map(|v| v.i64().map(|v| v as i32))
+                    .collect::<Result<BTreeSet<i32>, _>>()?;
+                let platform = parse_platform(ctx.args.try_get("platform")?.string()?)?;
+                let analysis = analyze(db, &ids, platform).await?;
+                json_output(&analysis)
+            })
+        },
+    )
+    .argument(InputValue::new(
+        "ids",
+        TypeRef::named_nn_list_nn(TypeRef::INT),
+    ))
+    .argument(InputValue::new(
+        "platform",
+        TypeRef::named_nn(TypeRef::STRING),
+    ))
+}
+
+async fn analyze(
+    db: &DatabaseConnection,
+    ids: &BTreeSet<i32>,
+    platform: Efeatureplatform,
+) -> Result<ConflictAnalysis, DError> {
+    let id_list: Vec<i32> = ids.iter().copied().collect();
+    let existing: BTreeSet<i32> = feature_config::Entity::find()
+        .select_only()
+        .column(feature_config::Column::Id)
+        .filter(feature_config::Column::Id.is_in(id_list.clone()))
+        .filter(feature_config::Column::Platform.eq(platform.clone()))
+        .filter(
+            Condition::any()
+                .add(feature_config::Column::IsDelete.is_null())
+                .add(feature_config::Column::IsDelete.eq(false)),
+        )
+        .into_tuple::<i32>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+    let rows = feature_config_conflict::Entity::find()
+        .filter(feature_config_conflict::Column::Platform.eq(platform))
+        .filter(feature_config_conflict::Column::IsDelete.eq(false))
+        .filter(feature_config_conflict::Column::IdBase.is_in(id_list.clone()))
+        .filter(feature_config_conflict::Column::IdConflict.is_in(id_list))
+        .all(db)
+        .await?;
+
+    let mut groups = UnionFind::default();
+    let mut pairs = vec![];
+    for row in rows {
+        match row.conflict_type {
+            Econflicttype::Ignored => continue,
+            Econflicttype::Conflict => groups.union(row.id_base, row.id_conflict),
+            Econflicttype::Compatible => {}
+        }
+        pairs.push(ConflictPair {
+            id: row.id,
+            id_base: row.id_base,
+            id_conflict: row.id_conflict,
+            conflict_type: row.conflict_type.to_value(),
+            comment: row.comment,
+        });
+    }
+    let conflict_groups = groups.groups();
+    let missing_ids: Vec<i32> = ids.difference(&existing).copied().collect();
+    Ok(ConflictAnalysis {
+        shippable: conflict_groups.is_empty() && missing_ids.is_empty(),
+        pairs,
+        conflict_groups,
+        missing_ids,
+    })
+}
+
+/// 冲突关系的并查集, 用于求传递冲突分组
+#[derive(Default)]
+struct UnionFind {
+    parent: HashMap<i32, i32>,
+}
+
+impl UnionFind {
+    fn find(&mut self, id: i32) -> i32 {
+        let p = *self.parent.entry(id).or_insert(id);
+        if p == id {
+            return id;
+        }
+        let root = self.find(p);
+        self.parent.insert(id, root);
+        root
+    }
+
+    fn union(&mut self, a: i32, b: i32) {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra != rb {
+            self.parent.insert(ra.max(rb), ra.min(rb));
+        }
+    }
+
+    fn groups(mut self) -> Vec<Vec<i32>> {
+        let ids: Vec<i32> = self.parent.keys().copied().collect();
+        let mut groups: BTreeMap<i32, Vec<i32>> = BTreeMap::new();
+        for id in ids {
+            let root = self.find(id);
+            groups.entry(root).or_default().push(id);
+        }
+        groups
+            .into_values()
+            .filter(|g| g.len() > 1)
+            .map(|mut g| {
+                g.sort();
+                g
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UnionFind;
+
+    fn groups(pairs: &[(i32, i32)]) -> Vec<Vec<i32>> {
+        let mut uf = UnionFind::default();
+        for (a, b) in pairs {
+            uf.union(*a, *b);
+        }
+        uf.groups()
+    }
+
+    #[test]
+    fn empty_has_no_groups() {
+        assert!(groups(&[]).is_empty());
+    }
+
+    #[test]
+    fn merges_transitive_conflicts() {
+        assert_eq!(
+            groups(&[(3, 1), (5, 3), (7, 8)]),
+            vec![vec![1, 3, 5], vec![7, 8]]
+        );
+    }
+
+    #[test]
+    fn joins_two_existing_groups() {
+        assert_eq!(groups(&[(1, 2), (3, 4), (4, 2)]), vec![vec![1, 2, 3, 4]]);
+    }
+
+    #[test]
+    fn duplicate_and_reversed_pairs_are_idempotent() {
+        assert_eq!(groups(&[(1, 2), (2, 1), (1, 2)]), vec![vec![1, 2]]);
+    }
+
+    #[test]
+    fn self_conflict_is_not_a_group() {
+        assert!(groups(&[(4, 4)]).is_empty());
+    }
+
+    #[test]
+    fn handles_negative_ids_and_long_chains() {
+        let chain: Vec<(i32, i32)> = (0..1000).map(|i| (i + 1, i)).collect();
+        let result = groups(&chain);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].len(), 1001);
+        assert_eq!(groups(&[(-1, 0)]), vec![vec![-1, 0]]);
+    }
+}
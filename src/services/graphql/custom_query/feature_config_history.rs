@@ -0,0 +1,316 @@
+use chrono::{DateTime, NaiveDateTime};
+use entity_graphql::sea_orm_active_enums::Efeatureplatform;
+use entity_graphql::{feature_config, feature_config_history};
+use sea_orm::sea_query::{Expr, SimpleExpr};
+use sea_orm::{
+    ActiveEnum, ColumnTrait, DatabaseConnection, EntityTrait, Iterable, Order, QueryFilter,
+    QueryOrder, QuerySelect,
+};
+use seaography::async_graphql::dynamic::{
+    Field, FieldFuture, InputValue, ResolverContext, TypeRef,
+};
+use seaography::async_graphql::Error;
+use seaography::Builder;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::{diff_fields, json_object, json_output, FieldChange, JsonField};
+use crate::error::{DError, LogicErr};
+use crate::services::graphql::LABELS_SELECT_EXPR;
+
+/// 快照的生效时间: 优先编辑时间, 缺失时取创建时间
+const EFFECTIVE_TIME_EXPR: &str = "COALESCE(\"editer_time\", \"create_time\")";
+
+/// 比较快照时忽略的来源信息
+const IGNORED_FIELDS: &[&str] = &["featureId", "source", "revisionId", "effectiveTime"];
+
+/// 需要给出结构化差异的 json 列
+const JSON_FIELDS: &[&str] = &[
+    "featureParam",
+    "strategyActual",
+    "strategyExpect",
+    "strategyDemotion",
+    "labels",
+    "gameMode",
+];
+
+/// 某一时刻单个 feature 的生效配置
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfigSnapshot {
+    feature_id: i32,
+    feature_key: String,
+    // history | current
+    source: &'static str,
+    // 来源记录的主键(history.id 或 feature_config.id)
+    revision_id: i32,
+    effective_time: Option<String>,
+    feature_type: Option<String>,
+    feature_desc: Option<String>,
+    feature_param: Value,
+    strategy_actual: Option<Value>,
+    strategy_expect: Option<Value>,
+    strategy_demotion: Option<Value>,
+    labels: Option<Value>,
+    game_mode: Option<Value>,
+    #[serde(skip)]
+    effective_at: Option<NaiveDateTime>,
+    #[serde(skip)]
+    is_delete: bool,
+}
+
+impl From<feature_config_history::Model> for ConfigSnapshot {
+    fn from(m: feature_config_history::Model) -> Self {
+        let effective_at = m.editer_time.or(m.create_time);
+        ConfigSnapshot {
+            feature_id: m.feature_id,
+            feature_key: m.feature_key,
+            source: "history",
+            revision_id: m.id,
+            effective_time: effective_at.map(|t| t.to_string()),
+            feature_type: m.feature_type,
+            feature_desc: m.feature_desc,
+            feature_param: m.feature_param,
+            strategy_actual: m.strategy_actual,
+            strategy_expect: m.strategy_expect,
+            strategy_demotion: m.strategy_demotion,
+            labels: m.labels,
+            game_mode: m.game_mode,
+            effective_at,
+            is_delete: m.is_delete.unwrap_or(false),
+        }
+    }
+}
+
+impl From<feature_config::Model> for ConfigSnapshot {
+    fn from(m: feature_config::Model) -> Self {
+        let effective_at = m.editer_time.or(m.create_time);
+        ConfigSnapshot {
+            feature_id: m.feature_id,
+            feature_key: m.feature_key,
+            source: "current",
+            revision_id: m.id,
+            effective_time: effective_at.map(|t| t.to_string()),
+            feature_type: m.feature_type,
+            feature_desc: m.feature_desc,
+            feature_param: m.feature_param,
+            strategy_actual: m.strategy_actual,
+            strategy_expect: m.strategy_expect,
+            strategy_demotion: m.strategy_demotion,
+            labels: m.labels,
+            game_mode: m.game_mode,
+            effective_at,
+            is_delete: m.is_delete.unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfigChange {
+    feature_id: i32,
+    feature_key: String,
+    changes: Vec<FieldChange>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfigSetDiff {
+    added: Vec<ConfigSnapshot>,
+    removed: Vec<ConfigSnapshot>,
+    changed: Vec<ConfigChange>,
+}
+
+pub fn register(builder: &mut Builder) {
+    builder.outputs.push(json_object(
+        "FeatureConfigSnapshot",
+        vec![
+            JsonField::Scalar("featureId", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("featureKey", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("source", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("revisionId", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("effectiveTime", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("featureType", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("featureDesc", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("featureParam", TypeRef::named_nn("Json")),
+            JsonField::Scalar("strategyActual", TypeRef::named("Json")),
+            JsonField::Scalar("strategyExpect", TypeRef::named("Json")),
+            JsonField::Scalar("strategyDemotion", TypeRef::named("Json")),
+            JsonField::Scalar("labels", TypeRef::named("Json")),
+            JsonField::Scalar("gameMode", TypeRef::named("Json")),
+        ],
+    ));
+    builder.outputs.push(json_object(
+        "FeatureConfigChange",
+        vec![
+            JsonField::Scalar("featureId", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("featureKey", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Object("changes", TypeRef::named_nn_list_nn("FieldChange")),
+        ],
+    ));
+    builder.outputs.push(json_object(
+        "FeatureConfigSetDiff",
+        vec![
+            JsonField::Object("added", TypeRef::named_nn_list_nn("FeatureConfigSnapshot")),
+            JsonField::Object(
+                "removed",
+                TypeRef::named_nn_list_nn("FeatureConfigSnapshot"),
+            ),
+            JsonField::Object("changed", TypeRef::named_nn_list_nn("FeatureConfigChange")),
+        ],
+    ));
+    builder.queries.push(feature_config_as_of());
+    builder.queries.push(feature_config_diff_between());
+}
+
+///
+/// 重建某平台在时刻 `at` 的全部生效 feature_config
+fn feature_config_as_of() -> Field {
+    Field::new(
+        "featureConfigAsOf",
+        TypeRef::named_nn_list_nn("FeatureConfigSnapshot"),
+        |ctx| {
+            FieldFuture::new(async move {
+                let db = ctx.data::<DatabaseConnection>()?;
+                let platform = platform_arg(&ctx)?;
+                let at = time_arg(&ctx, "at")?;
+                let snapshots = config_set_as_of(db, platform, at).await?;
+                json_output(&snapshots.into_values().collect::<Vec<_>>())
+            })
+        },
+    )
+    .argument(InputValue::new(
+        "platform",
+        TypeRef::named_nn(TypeRef::STRING),
+    ))
+    .argument(InputValue::new("at", TypeRef::named_nn(TypeRef::STRING)))
+}
+
+///
+/// 比较某平台在两个时刻的生效 feature_config 集合
+fn feature_config_diff_between() -> Field {
+    Field::new(
+        "featureConfigDiffBetween",
+        TypeRef::named_nn("FeatureConfigSetDiff"),
+        |ctx| {
+            FieldFuture::new(async move {
+                let db = ctx.data::<DatabaseConnection>()?;
+                let platform = platform_arg(&ctx)?;
+                let from = time_arg(&ctx, "from")?;
+                let to = time_arg(&ctx, "to")?;
+                let mut before = config_set_as_of(db, platform.clone(), from).await?;
+                let after = config_set_as_of(db, platform, to).await?;
+                let mut diff = ConfigSetDiff {
+                    added: vec![],
+                    removed: vec![],
+                    changed: vec![],
+                };
+                for (feature_id, cur) in after {
+                    let Some(prev) = before.remove(&feature_id) else {
+                        diff.added.push(cur);
+                        continue;
+                    };
+                    let changes = diff_fields(
+                        &serde_json::to_value(&prev)?,
+                        &serde_json::to_value(&cur)?,
+                        IGNORED_FIELDS,
+                        JSON_FIELDS,
+                    );
+                    if !changes.is_empty() {
+                        diff.changed.push(ConfigChange {
+                            feature_id,
+                            feature_key: cur.feature_key,
+                            changes,
+                        });
+                    }
+                }
+                diff.removed = before.into_values().collect();
+                json_output(&diff)
+            })
+        },
+    )
+    .argument(InputValue::new(
+        "platform",
+        TypeRef::named_nn(TypeRef::STRING),
+    ))
+    .argument(InputValue::new("from", TypeRef::named_nn(TypeRef::STRING)))
+    .argument(InputValue::new("to", TypeRef::named_nn(TypeRef::STRING)))
+}
+
+///
+/// 时刻 `at` 的生效配置集合(按 feature_id):
+/// 每个 feature 取生效时间不晚于 `at` 的最新快照, 当前表记录视为其编辑时间的快照(兼容未写历史的配置),
+/// 最新快照为删除状态的 feature 不计入
+async fn config_set_as_of(
+    db: &DatabaseConnection,
+    platform: Efeatureplatform,
+    at: NaiveDateTime,
+) -> Result<BTreeMap<i32, ConfigSnapshot>, DError> {
+    let effective_time = || SimpleExpr::from(Expr::cust(EFFECTIVE_TIME_EXPR));
+    let history = feature_config_history::Entity::find()
+        .select_only()
+        .columns(
+            feature_config_history::Column::iter()
+                .filter(|c| !matches!(c, feature_config_history::Column::Labels)),
+        )
+        .expr_as(Expr::cust(LABELS_SELECT_EXPR), "labels")
+        .filter(feature_config_history::Column::Platform.eq(platform.clone()))
+        .filter(Expr::cust_with_values(
+            format!("{} <= $1", EFFECTIVE_TIME_EXPR),
+            [at],
+        ))
+        .distinct_on([feature_config_history::Column::FeatureId])
+        .order_by_asc(feature_config_history::Column::FeatureId)
+        .order_by(effective_time(), Order::Desc)
+        .order_by_desc(feature_config_history::Column::Id)
+        .into_model::<feature_config_history::Model>()
+        .all(db)
+        .await?;
+    let current = feature_config::Entity::find()
+        .select_only()
+        .columns(
+            feature_config::Column::iter().filter(|c| !matches!(c, feature_config::Column::Labels)),
+        )
+        .expr_as(Expr::cust(LABELS_SELECT_EXPR), "labels")
+        .filter(feature_config::Column::Platform.eq(platform))
+        .filter(Expr::cust_with_values(
+            format!("{} <= $1", EFFECTIVE_TIME_EXPR),
+            [at],
+        ))
+        .into_model::<feature_config::Model>()
+        .all(db)
+        .await?;
+
+    let mut latest: BTreeMap<i32, ConfigSnapshot> = BTreeMap::new();
+    let snapshots = history
+        .into_iter()
+        .map(ConfigSnapshot::from)
+        .chain(current.into_iter().map(ConfigSnapshot::from));
+    for snap in snapshots {
+        match latest.get(&snap.feature_id) {
+            Some(prev) if prev.effective_at > snap.effective_at => {}
+            _ => {
+                latest.insert(snap.feature_id, snap);
+            }
+        }
+    }
+    latest.retain(|_, s| !s.is_delete);
+    Ok(latest)
+}
+
+fn platform_arg(ctx: &ResolverContext) -> Result<Efeatureplatform, Error> {
+    let name = ctx.args.try_get("platform")?.string()?;
+    Efeatureplatform::try_from_value(&name.to_owned())
+        .map_err(|_| DError::Custom(LogicErr::ParamsError(format!("platform <{}>", name))).into())
+}
+
+///
+/// 时间参数: RFC3339(按所给时区的本地时间比较)或 `YYYY-MM-DD HH:MM:SS`
+fn time_arg(ctx: &ResolverContext, name: &str) -> Result<NaiveDateTime, Error> {
+    let v = ctx.args.try_get(name)?.string()?;
+    DateTime::parse_from_rfc3339(v)
+        .map(|t| t.naive_local())
+        .or_else(|_| NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S"))
+        .map_err(|_| DError::Custom(LogicErr::ParamsError(format!("{} <{}>", name, v))).into())
+}
@@ -0,0 +1,110 @@
+use sea_orm::DatabaseConnection;
+use seaography::async_graphql::dynamic::{Field, FieldFuture, InputObject, InputValue, TypeRef};
+use seaography::Builder;
+
+use super::{json_object, json_output, JsonField};
+use crate::feature_tag::eval::PlayerContext;
+use crate::feature_tag::service;
+
+pub fn register(builder: &mut Builder) {
+    builder.inputs.push(
+        InputObject::new("PlayerContextInput")
+            .field(InputValue::new("platform", TypeRef::named(TypeRef::STRING)))
+            .field(InputValue::new(
+                "appVersion",
+                TypeRef::named(TypeRef::STRING),
+            ))
+            .field(InputValue::new("country", TypeRef::named(TypeRef::STRING)))
+            .field(InputValue::new("attrs", TypeRef::named("Json"))),
+    );
+    builder.outputs.push(json_object(
+        "PlayerContext",
+        vec![
+            JsonField::Scalar("platform", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("appVersion", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("country", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("attrs", TypeRef::named_nn("Json")),
+        ],
+    ));
+    builder.outputs.push(json_object(
+        "TagClauseResult",
+        vec![
+            JsonField::Scalar("field", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("op", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("expected", TypeRef::named("Json")),
+            JsonField::Scalar("actual", TypeRef::named("Json")),
+            JsonField::Scalar("matched", TypeRef::named_nn(TypeRef::BOOLEAN)),
+            JsonField::Scalar("detail", TypeRef::named(TypeRef::STRING)),
+        ],
+    ));
+    builder.outputs.push(json_object(
+        "TagConditionResult",
+        vec![
+            JsonField::Scalar("matchType", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("matched", TypeRef::named_nn(TypeRef::BOOLEAN)),
+            JsonField::Object("clauses", TypeRef::named_nn_list_nn("TagClauseResult")),
+        ],
+    ));
+    builder.outputs.push(json_object(
+        "FeatureTagResult",
+        vec![
+            JsonField::Scalar("id", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("platform", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("newName", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("tagDesc", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("state", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("matched", TypeRef::named_nn(TypeRef::BOOLEAN)),
+            JsonField::Object("condition", TypeRef::named("TagConditionResult")),
+            JsonField::Scalar("error", TypeRef::named(TypeRef::STRING)),
+        ],
+    ));
+    builder.outputs.push(json_object(
+        "ResolvedFeature",
+        vec![
+            JsonField::Scalar("featureKey", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("featureId", TypeRef::named(TypeRef::INT)),
+            JsonField::Scalar("featureParam", TypeRef::named("Json")),
+            JsonField::Scalar("tagId", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("shadowedTagIds", TypeRef::named_nn_list_nn(TypeRef::INT)),
+        ],
+    ));
+    builder.outputs.push(json_object(
+        "FeatureTagEvaluation",
+        vec![
+            JsonField::Object("context", TypeRef::named_nn("PlayerContext")),
+            JsonField::Object("matchedTags", TypeRef::named_nn_list_nn("FeatureTagResult")),
+            JsonField::Object(
+                "unmatchedTags",
+                TypeRef::named_nn_list_nn("FeatureTagResult"),
+            ),
+            JsonField::Object(
+                "unsupportedTags",
+                TypeRef::named_nn_list_nn("FeatureTagResult"),
+            ),
+            JsonField::Object("features", TypeRef::named_nn_list_nn("ResolvedFeature")),
+            JsonField::Scalar("unresolved", TypeRef::named_nn_list_nn("Json")),
+        ],
+    ));
+    builder.queries.push(evaluate_feature_tags());
+}
+
+///
+/// 对玩家上下文判定全部未删除的 feature_tag_config, 给出命中标签、下发的 feature 参数及逐条子句的判定说明
+fn evaluate_feature_tags() -> Field {
+    Field::new(
+        "evaluateFeatureTags",
+        TypeRef::named_nn("FeatureTagEvaluation"),
+        |ctx| {
+            FieldFuture::new(async move {
+                let db = ctx.data::<DatabaseConnection>()?;
+                let player: PlayerContext = ctx.args.try_get("context")?.deserialize()?;
+                let evaluation = service::evaluate(db, player).await?;
+                json_output(&evaluation)
+            })
+        },
+    )
+    .argument(InputValue::new(
+        "context",
+        TypeRef::named_nn("PlayerContextInput"),
+    ))
+}
@@ -0,0 +1,452 @@
+use entity_graphql::{
+    feature_config_label_lv1, feature_config_label_lv2, feature_config_label_lv3,
+    feature_config_label_lv4,
+};
+use sea_orm::{
+    ActiveEnum, DatabaseConnection, DbBackend, EntityTrait, FromQueryResult, QueryFilter,
+    QueryOrder, Statement,
+};
+use seaography::async_graphql::dynamic::{Field, FieldFuture, InputValue, TypeRef};
+use seaography::Builder;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::{json_object, json_output, not_deleted, parse_ids, JsonField};
+use crate::error::{DError, LogicErr};
+use crate::services::graphql::LABELS_SELECT_EXPR;
+
+const MAX_LEVEL: usize = 4;
+
+/// 按 `labels` 路径分组统计 feature_config
+const LABEL_PATH_COUNT_SQL: &str =
+    "SELECT {labels} AS labels, count(*) AS cnt FROM feature_config \
+     WHERE labels IS NOT NULL AND is_delete IS NOT TRUE GROUP BY labels";
+
+/// 四张层级表的统一结构
+#[derive(Debug, Clone)]
+struct LabelRow {
+    // 1..=4
+    level: usize,
+    id: i32,
+    label: String,
+    ltype: String,
+    algo_type: Option<String>,
+    // 上一层的 label id, lv1 为空
+    parents: Vec<i32>,
+    // lv4 固定为叶子
+    is_leaf: bool,
+    comment: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LabelNode {
+    level: usize,
+    id: i32,
+    label: String,
+    ltype: String,
+    algo_type: Option<String>,
+    is_leaf: bool,
+    comment: Option<String>,
+    // 从根到本节点的 label id
+    path: Vec<i32>,
+    // labels 路径经过本节点的 feature_config 数
+    feature_config_count: i64,
+    children: Vec<LabelNode>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LabelPathCheck {
+    label_ids: Vec<i32>,
+    valid: bool,
+    errors: Vec<String>,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct LabelPathCount {
+    labels: Value,
+    cnt: i64,
+}
+
+/// 全部层级的 label, 按层级下标(0..4)存放
+struct LabelIndex {
+    levels: Vec<Vec<LabelRow>>,
+}
+
+impl LabelIndex {
+    fn get(&self, level: usize, id: i32) -> Option<&LabelRow> {
+        self.levels
+            .get(level.wrapping_sub(1))?
+            .iter()
+            .find(|r| r.id == id)
+    }
+
+    fn children<'a>(&'a self, parent: &LabelRow) -> impl Iterator<Item = &'a LabelRow> {
+        let (parent_id, is_leaf) = (parent.id, parent.is_leaf);
+        self.levels
+            .get(parent.level)
+            .into_iter()
+            .flatten()
+            .filter(move |r| !is_leaf && r.parents.contains(&parent_id))
+    }
+
+    ///
+    /// 构建节点及其子树; `counts` 以 `path` 为键, 相对子树根所在层级
+    fn node(&self, row: &LabelRow, path: Vec<i32>, counts: &HashMap<Vec<i32>, i64>) -> LabelNode {
+        let children = self
+            .children(row)
+            .map(|child| {
+                let mut child_path = path.clone();
+                child_path.push(child.id);
+                self.node(child, child_path, counts)
+            })
+            .collect();
+        LabelNode {
+            level: row.level,
+            id: row.id,
+            label: row.label.clone(),
+            ltype: row.ltype.clone(),
+            algo_type: row.algo_type.clone(),
+            is_leaf: row.is_leaf,
+            comment: row.comment.clone(),
+            feature_config_count: counts.get(&path).copied().unwrap_or(0),
+            path,
+            children,
+        }
+    }
+}
+
+pub fn register(builder: &mut Builder) {
+    builder.outputs.push(json_object(
+        "LabelNode",
+        vec![
+            JsonField::Scalar("level", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("id", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("label", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("ltype", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("algoType", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("isLeaf", TypeRef::named_nn(TypeRef::BOOLEAN)),
+            JsonField::Scalar("comment", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("path", TypeRef::named_nn_list_nn(TypeRef::INT)),
+            JsonField::Scalar("featureConfigCount", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Object("children", TypeRef::named_nn_list_nn("LabelNode")),
+        ],
+    ));
+    builder.outputs.push(json_object(
+        "LabelPathCheck",
+        vec![
+            JsonField::Scalar("labelIds", TypeRef::named_nn_list_nn(TypeRef::INT)),
+            JsonField::Scalar("valid", TypeRef::named_nn(TypeRef::BOOLEAN)),
+            JsonField::Scalar("errors", TypeRef::named_nn_list_nn(TypeRef::STRING)),
+        ],
+    ));
+    builder.queries.push(label_tree());
+    builder.queries.push(validate_label_paths());
+}
+
+///
+/// label 层级树: 不传参数返回以 lv1 为根的整棵树, 传 `level` + `id` 返回该节点的子树
+///
+/// feature_config.labels 按层级顺序保存从 lv1 到叶子的 label id, 计数按路径前缀统计;
+/// 多个父节点的 label 会出现在每个父节点下
+fn label_tree() -> Field {
+    Field::new("labelTree", TypeRef::named_nn_list_nn("LabelNode"), |ctx| {
+        FieldFuture::new(async move {
+            let db = ctx.data::<DatabaseConnection>()?;
+            let level = ctx.args.get("level").map(|v| v.u64()).transpose()?;
+            let id = ctx.args.get("id").map(|v| v.i64()).transpose()?;
+            let index = load_labels(db).await?;
+            let roots: Vec<&LabelRow> = match (level, id) {
+                (None, None) => index.levels[0].iter().collect(),
+                (Some(level), Some(id)) => {
+                    let row = index.get(level as usize, id as i32).ok_or_else(|| {
+                        DError::Custom(LogicErr::NotFound(format!(
+                            "feature_config_label_lv{} <{}>",
+                            level, id
+                        )))
+                    })?;
+                    vec![row]
+                }
+                _ => {
+                    return Err(DError::Custom(LogicErr::ParamsError(
+                        "level and id must be given together".to_owned(),
+                    ))
+                    .into())
+                }
+            };
+            let start = roots.first().map_or(1, |r| r.level);
+            let counts = path_counts(db, start).await?;
+            let nodes: Vec<LabelNode> = roots
+                .into_iter()
+                .map(|r| index.node(r, vec![r.id], &counts))
+                .collect();
+            json_output(&nodes)
+        })
+    })
+    .argument(InputValue::new("level", TypeRef::named(TypeRef::INT)))
+    .argument(InputValue::new("id", TypeRef::named(TypeRef::INT)))
+}
+
+///
+/// 校验每组 label id 是否为从 lv1 开始、逐层父子相连并止于叶子的合法路径
+fn validate_label_paths() -> Field {
+    Field::new(
+        "validateLabelPaths",
+        TypeRef::named_nn_list_nn("LabelPathCheck"),
+        |ctx| {
+            FieldFuture::new(async move {
+                let db = ctx.data::<DatabaseConnection>()?;
+                let paths = ctx
+                    .args
+                    .try_get("paths")?
+                    .list()?
+                    .iter()
+                    .map(|p| {
+                        p.list()?
+                            .iter()
+                            .map(|v| v.i64().map(|v| v as i32))
+                            .collect::<Result<Vec<i32>, _>>()
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let index = load_labels(db).await?;
+                let checks: Vec<LabelPathCheck> =
+                    paths.into_iter().map(|p| check_path(&index, p)).collect();
+                json_output(&checks)
+            })
+        },
+    )
+    .argument(InputValue::new(
+        "paths",
+        TypeRef::NonNull(Box::new(TypeRef::List(Box::new(
+            TypeRef::named_nn_list_nn(TypeRef::INT),
+        )))),
+    ))
+}
+
+fn check_path(index: &LabelIndex, label_ids: Vec<i32>) -> LabelPathCheck {
+    let mut errors = vec![];
+    if label_ids.is_empty() {
+        errors.push("empty path".to_owned());
+    }
+    if label_ids.len() > MAX_LEVEL {
+        errors.push(format!("path deeper than {} levels", MAX_LEVEL));
+    }
+    let mut prev: Option<&LabelRow> = None;
+    for (i, id) in label_ids.iter().take(MAX_LEVEL).enumerate() {
+        let level = i + 1;
+        let Some(row) = index.get(level, *id) else {
+            errors.push(format!("lv{} <{}> not found", level, id));
+            prev = None;
+            continue;
+        };
+        if let Some(parent) = prev {
+            if parent.is_leaf {
+                errors.push(format!("lv{} <{}> is a leaf", parent.level, parent.id));
+            }
+            if !row.parents.contains(&parent.id) {
+                errors.push(format!(
+                    "lv{} <{}> is not a child of lv{} <{}>",
+                    level, id, parent.level, parent.id
+                ));
+            }
+        }
+        prev = Some(row);
+    }
+    if let (Some(last), true) = (prev, label_ids.len() <= MAX_LEVEL) {
+        if !last.is_leaf {
+            errors.push(format!("lv{} <{}> is not a leaf", last.level, last.id));
+        }
+    }
+    LabelPathCheck {
+        label_ids,
+        valid: errors.is_empty(),
+        errors,
+    }
+}
+
+///
+/// 读取四层未删除的 label
+async fn load_labels(db: &DatabaseConnection) -> Result<LabelIndex, DError> {
+    let lv1 = feature_config_label_lv1::Entity::find()
+        .filter(not_deleted(feature_config_label_lv1::Column::IsDelete))
+        .order_by_asc(feature_config_label_lv1::Column::Id)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|m| LabelRow {
+            level: 1,
+            id: m.id,
+            label: m.label,
+            ltype: m.ltype.to_value(),
+            algo_type: None,
+            parents: vec![],
+            is_leaf: m.is_leaf.unwrap_or(false),
+            comment: m.comment,
+        })
+        .collect();
+    let lv2 = feature_config_label_lv2::Entity::find()
+        .filter(not_deleted(feature_config_label_lv2::Column::IsDelete))
+        .order_by_asc(feature_config_label_lv2::Column::Id)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|m| LabelRow {
+            level: 2,
+            id: m.id,
+            label: m.label,
+            ltype: m.ltype.to_value(),
+            algo_type: Some(m.algo_type.to_value()),
+            parents: parse_ids(&m.parents),
+            is_leaf: m.is_leaf.unwrap_or(false),
+            comment: m.comment,
+        })
+        .collect();
+    let lv3 = feature_config_label_lv3::Entity::find()
+        .filter(not_deleted(feature_config_label_lv3::Column::IsDelete))
+        .order_by_asc(feature_config_label_lv3::Column::Id)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|m| LabelRow {
+            level: 3,
+            id: m.id,
+            label: m.label,
+            ltype: m.ltype.to_value(),
+            algo_type: Some(m.algo_type.to_value()),
+            parents: parse_ids(&m.parents),
+            is_leaf: m.is_leaf.unwrap_or(false),
+            comment: m.comment,
+        })
+        .collect();
+    // lv4 的 label 列即为 Labeltype
+    let lv4 = feature_config_label_lv4::Entity::find()
+        .filter(not_deleted(feature_config_label_lv4::Column::IsDelete))
+        .order_by_asc(feature_config_label_lv4::Column::Id)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|m| LabelRow {
+            level: 4,
+            id: m.id,
+            label: m.label.to_value(),
+            ltype: m.label.to_value(),
+            algo_type: None,
+            parents: parse_ids(&m.parents),
+            is_leaf: true,
+            comment: m.comment,
+        })
+        .collect();
+    Ok(LabelIndex {
+        levels: vec![lv1, lv2, lv3, lv4],
+    })
+}
+
+///
+/// 统计从第 `start` 层开始的各路径前缀对应的 feature_config 数
+async fn path_counts(
+    db: &DatabaseConnection,
+    start: usize,
+) -> Result<HashMap<Vec<i32>, i64>, DError> {
+    let rows = LabelPathCount::find_by_statement(Statement::from_string(
+        DbBackend::Postgres,
+        LABEL_PATH_COUNT_SQL.replace("{labels}", LABELS_SELECT_EXPR),
+    ))
+    .all(db)
+    .await?;
+    let mut counts: HashMap<Vec<i32>, i64> = HashMap::new();
+    for row in rows {
+        let ids = parse_ids(&row.labels);
+        for end in start..=ids.len() {
+            *counts.entry(ids[start - 1..end].to_vec()).or_default() += row.cnt;
+        }
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(level: usize, id: i32, parents: Vec<i32>, is_leaf: bool) -> LabelRow {
+        LabelRow {
+            level,
+            id,
+            label: format!("l{}", id),
+            ltype: "t".to_owned(),
+            algo_type: None,
+            parents,
+            is_leaf,
+            comment: None,
+        }
+    }
+
+    // lv1: 1 -> lv2: 10 -> lv3: 100(叶子); lv1: 1 -> lv2: 11(叶子); lv1: 9(叶子) -> lv2: 12
+    fn index() -> LabelIndex {
+        LabelIndex {
+            levels: vec![
+                vec![row(1, 1, vec![], false), row(1, 9, vec![], true)],
+                vec![
+                    row(2, 10, vec![1], false),
+                    row(2, 11, vec![1], true),
+                    row(2, 12, vec![9], false),
+                ],
+                vec![row(3, 100, vec![10], true)],
+                vec![],
+            ],
+        }
+    }
+
+    fn errors(ids: Vec<i32>) -> Vec<String> {
+        let check = check_path(&index(), ids);
+        assert_eq!(check.valid, check.errors.is_empty());
+        check.errors
+    }
+
+    #[test]
+    fn accepts_paths_ending_at_a_leaf() {
+        assert!(errors(vec![1, 10, 100]).is_empty());
+        assert!(errors(vec![1, 11]).is_empty());
+        assert!(errors(vec![9]).is_empty());
+    }
+
+    #[test]
+    fn rejects_empty_path() {
+        assert_eq!(errors(vec![]), vec!["empty path"]);
+    }
+
+    #[test]
+    fn rejects_path_not_ending_at_a_leaf() {
+        assert_eq!(errors(vec![1, 10]), vec!["lv2 <10> is not a leaf"]);
+    }
+
+    #[test]
+    fn rejects_wrong_parent_and_children_of_leaf() {
+        assert_eq!(
+            errors(vec![1, 12]),
+            vec![
+                "lv2 <12> is not a child of lv1 <1>",
+                "lv2 <12> is not a leaf"
+            ]
+        );
+        assert_eq!(
+            errors(vec![9, 12]),
+            vec!["lv1 <9> is a leaf", "lv2 <12> is not a leaf"]
+        );
+    }
+
+    #[test]
+    fn unknown_id_skips_parent_check_of_next_level() {
+        // id 只在其它层存在时按未找到处理, 下一层不再与缺失的上层比较
+        assert_eq!(errors(vec![1, 100, 100]), vec!["lv2 <100> not found"]);
+    }
+
+    #[test]
+    fn rejects_paths_deeper_than_max_level() {
+        assert_eq!(
+            errors(vec![1, 10, 100, 5, 6]),
+            vec!["path deeper than 4 levels", "lv4 <5> not found"]
+        );
+    }
+}
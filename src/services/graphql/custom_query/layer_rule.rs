@@ -0,0 +1,405 @@
+use entity_graphql::{
+    feature_config, feature_config_layer_rule_ids, feature_config_layer_rule_zh_cn,
+    feature_config_layers, feature_config_layers_sol_all,
+};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, Iterable, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use seaography::async_graphql::dynamic::{
+    Field, FieldFuture, FieldValue, InputValue, Object, TypeRef,
+};
+use seaography::Builder;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{escape_like, json_object, json_output, not_deleted, parse_ids, JsonField};
+use crate::error::{DError, LogicErr};
+use crate::services::graphql::LABELS_SELECT_EXPR;
+
+/// 层(feature_config_layers 或 feature_config_layers_sol_all)
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LayerInfo {
+    layer_name: String,
+    layer_prefix: Option<String>,
+    layer_level: Option<String>,
+    // layers | layers_sol_all, 两表均无该层时为空(仅存在规则)
+    source: Option<&'static str>,
+}
+
+/// 一类策略(actual/expect/demotion)关联的 feature_config
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StrategyGroup {
+    kind: &'static str,
+    strategy_type: Option<String>,
+    strategy_type_zh_cn: Option<String>,
+    strategy_zh_cn: Option<String>,
+    feature_config_ids: Vec<i32>,
+    // 规则中引用但不存在或已删除的配置
+    missing_ids: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LayerRuleResolution {
+    layer: LayerInfo,
+    layer_name_zh_cn: Option<String>,
+    label_ids: Vec<i32>,
+    // lv1..lv4 的中文名
+    labels_zh_cn: Vec<Option<String>>,
+    strategies: Vec<StrategyGroup>,
+}
+
+pub fn register(builder: &mut Builder) {
+    builder.outputs.push(json_object(
+        "LayerInfo",
+        vec![
+            JsonField::Scalar("layerName", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("layerPrefix", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("layerLevel", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("source", TypeRef::named(TypeRef::STRING)),
+        ],
+    ));
+    builder.outputs.push(strategy_group_object());
+    builder.outputs.push(json_object(
+        "LayerRuleResolution",
+        vec![
+            JsonField::Object("layer", TypeRef::named_nn("LayerInfo")),
+            JsonField::Scalar("layerNameZhCn", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("labelIds", TypeRef::named_nn_list_nn(TypeRef::INT)),
+            JsonField::Scalar("labelsZhCn", TypeRef::named_nn_list(TypeRef::STRING)),
+            JsonField::Object(
+                "strategies",
+                TypeRef::named_nn_list_nn("LayerStrategyGroup"),
+            ),
+        ],
+    ));
+    builder.queries.push(resolve_layer_rules());
+}
+
+///
+/// 策略分组, `featureConfigs` 按 id 加载为实体对象(可继续查询关联)
+fn strategy_group_object() -> Object {
+    json_object(
+        "LayerStrategyGroup",
+        vec![
+            JsonField::Scalar("kind", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("strategyType", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("strategyTypeZhCn", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("strategyZhCn", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("featureConfigIds", TypeRef::named_nn_list_nn(TypeRef::INT)),
+            JsonField::Scalar("missingIds", TypeRef::named_nn_list_nn(TypeRef::INT)),
+        ],
+    )
+    .field(Field::new(
+        "featureConfigs",
+        TypeRef::named_nn_list_nn("FeatureConfig"),
+        |ctx| {
+            FieldFuture::new(async move {
+                let db = ctx.data::<DatabaseConnection>()?;
+                let parent = ctx.parent_value.try_downcast_ref::<Value>()?;
+                let ids = parent
+                    .get("featureConfigIds")
+                    .map(parse_ids)
+                    .unwrap_or_default();
+                let rows = load_configs(db, &ids).await?;
+                Ok(Some(FieldValue::list(
+                    rows.into_iter().map(FieldValue::owned_any),
+                )))
+            })
+        },
+    ))
+}
+
+///
+/// 按层名或前缀解析分层规则: 层信息、中文名, 以及按 actual/expect/demotion 分组的 feature_config
+fn resolve_layer_rules() -> Field {
+    Field::new(
+        "resolveLayerRules",
+        TypeRef::named_nn_list_nn("LayerRuleResolution"),
+        |ctx| {
+            FieldFuture::new(async move {
+                let db = ctx.data::<DatabaseConnection>()?;
+                let name = ctx
+                    .args
+                    .get("layerName")
+                    .map(|v| v.string().map(str::to_owned))
+                    .transpose()?;
+                let prefix = ctx
+                    .args
+                    .get("prefix")
+                    .map(|v| v.string().map(str::to_owned))
+                    .transpose()?;
+                let layers = match (name, prefix) {
+                    (Some(name), None) => find_layers_by_name(db, &name).await?,
+                    (None, Some(prefix)) => find_layers_by_prefix(db, &prefix).await?,
+                    _ => {
+                        return Err(DError::Custom(LogicErr::ParamsError(
+                            "exactly one of layerName and prefix is required".to_owned(),
+                        ))
+                        .into())
+                    }
+                };
+                let mut resolved = vec![];
+                for layer in layers {
+                    resolved.extend(resolve(db, layer).await?);
+                }
+                json_output(&resolved)
+            })
+        },
+    )
+    .argument(InputValue::new(
+        "layerName",
+        TypeRef::named(TypeRef::STRING),
+    ))
+    .argument(InputValue::new("prefix", TypeRef::named(TypeRef::STRING)))
+}
+
+async fn find_layers_by_name(
+    db: &DatabaseConnection,
+    name: &str,
+) -> Result<Vec<LayerInfo>, DError> {
+    let layers = find_layers(
+        db,
+        Condition::all().add(feature_config_layers::Column::LayerName.eq(name)),
+        Condition::all().add(feature_config_layers_sol_all::Column::LayerName.eq(name)),
+    )
+    .await?;
+    if !layers.is_empty() {
+        return Ok(layers);
+    }
+    // 层表中缺失时仍按规则表解析
+    Ok(vec![LayerInfo {
+        layer_name: name.to_owned(),
+        layer_prefix: None,
+        layer_level: None,
+        source: None,
+    }])
+}
+
+///
+/// 前缀匹配 feature_config_layers.layer_prefix, 或层名以该前缀开头
+async fn find_layers_by_prefix(
+    db: &DatabaseConnection,
+    prefix: &str,
+) -> Result<Vec<LayerInfo>, DError> {
+    // starts_with 不转义前缀中的通配符
+    let pattern = format!("{}%", escape_like(prefix));
+    find_layers(
+        db,
+        Condition::any()
+            .add(feature_config_layers::Column::LayerPrefix.eq(prefix))
+            .add(feature_config_layers::Column::LayerName.like(pattern.as_str())),
+        Condition::all()
+            .add(feature_config_layers_sol_all::Column::LayerName.like(pattern.as_str())),
+    )
+    .await
+}
+
+///
+/// 两张层表中匹配的层, 同名时以 feature_config_layers 为准
+async fn find_layers(
+    db: &DatabaseConnection,
+    layers_cond: Condition,
+    sol_all_cond: Condition,
+) -> Result<Vec<LayerInfo>, DError> {
+    let mut found: BTreeMap<String, LayerInfo> = BTreeMap::new();
+    let sol_all = feature_config_layers_sol_all::Entity::find()
+        .filter(sol_all_cond)
+        .filter(not_deleted(feature_config_layers_sol_all::Column::IsDelete))
+        .all(db)
+        .await?;
+    for m in sol_all {
+        let Some(layer_name) = m.layer_name else {
+            continue;
+        };
+        found.insert(
+            layer_name.clone(),
+            LayerInfo {
+                layer_name,
+                layer_prefix: None,
+                layer_level: m.layer_level,
+                source: Some("layers_sol_all"),
+            },
+        );
+    }
+    let layers = feature_config_layers::Entity::find()
+        .filter(layers_cond)
+        .filter(not_deleted(feature_config_layers::Column::IsDelete))
+        .all(db)
+        .await?;
+    for m in layers {
+        let Some(layer_name) = m.layer_name else {
+            continue;
+        };
+        found.insert(
+            layer_name.clone(),
+            LayerInfo {
+                layer_name,
+                layer_prefix: m.layer_prefix,
+                layer_level: m.layer_level,
+                source: Some("layers"),
+            },
+        );
+    }
+    Ok(found.into_values().collect())
+}
+
+///
+/// 解析单个层的规则, 一个层可对应多条规则
+async fn resolve(
+    db: &DatabaseConnection,
+    layer: LayerInfo,
+) -> Result<Vec<LayerRuleResolution>, DError> {
+    let rules = feature_config_layer_rule_ids::Entity::find()
+        .filter(feature_config_layer_rule_ids::Column::LayerName.eq(&layer.layer_name))
+        .filter(not_deleted(feature_config_layer_rule_ids::Column::IsDelete))
+        .order_by_asc(feature_config_layer_rule_ids::Column::Id)
+        .all(db)
+        .await?;
+    let zh_cn = feature_config_layer_rule_zh_cn::Entity::find()
+        .filter(feature_config_layer_rule_zh_cn::Column::LayerName.eq(&layer.layer_name))
+        .filter(not_deleted(
+            feature_config_layer_rule_zh_cn::Column::IsDelete,
+        ))
+        .order_by_asc(feature_config_layer_rule_zh_cn::Column::Id)
+        .all(db)
+        .await?;
+    let ids_of = |v: &Option<Value>| v.as_ref().map(parse_ids).unwrap_or_default();
+    let referenced: BTreeSet<i32> = rules
+        .iter()
+        .flat_map(|r| {
+            [
+                &r.strategy_actual_ids,
+                &r.strategy_expect_ids,
+                &r.strategy_demotion_ids,
+            ]
+        })
+        .flat_map(ids_of)
+        .collect();
+    let existing = existing_config_ids(db, referenced.into_iter().collect()).await?;
+
+    let group =
+        |kind, strategy_type: &Option<String>, ids: Vec<i32>, type_zh, name_zh| StrategyGroup {
+            kind,
+            strategy_type: strategy_type.clone(),
+            strategy_type_zh_cn: type_zh,
+            strategy_zh_cn: name_zh,
+            missing_ids: ids
+                .iter()
+                .filter(|id| !existing.contains(id))
+                .copied()
+                .collect(),
+            feature_config_ids: ids,
+        };
+    let mut resolved = vec![];
+    for rule in &rules {
+        let zh_row = zh_cn_of(rule, &zh_cn);
+        let zh = |f: fn(&feature_config_layer_rule_zh_cn::Model) -> &Option<String>| {
+            zh_row.and_then(|m| f(m).clone())
+        };
+        let strategies = vec![
+            group(
+                "actual",
+                &rule.type_strategy_actual,
+                ids_of(&rule.strategy_actual_ids),
+                zh(|m| &m.type_strategy_actual),
+                zh(|m| &m.strategy_actual),
+            ),
+            group(
+                "expect",
+                &rule.type_strategy_expect,
+                ids_of(&rule.strategy_expect_ids),
+                zh(|m| &m.type_strategy_expect),
+                zh(|m| &m.strategy_expect),
+            ),
+            group(
+                "demotion",
+                &rule.type_strategy_demotion,
+                ids_of(&rule.strategy_demotion_ids),
+                zh(|m| &m.type_strategy_demotion),
+                zh(|m| &m.strategy_demotion),
+            ),
+        ];
+        resolved.push(LayerRuleResolution {
+            layer: LayerInfo {
+                layer_name: layer.layer_name.clone(),
+                layer_prefix: layer.layer_prefix.clone(),
+                layer_level: layer.layer_level.clone(),
+                source: layer.source,
+            },
+            layer_name_zh_cn: zh(|m| &m.layer_name),
+            label_ids: ids_of(&rule.label_ids),
+            labels_zh_cn: vec![
+                zh(|m| &m.label_lv1),
+                zh(|m| &m.label_lv2),
+                zh(|m| &m.label_lv3),
+                zh(|m| &m.label_lv4),
+            ],
+            strategies,
+        });
+    }
+    Ok(resolved)
+}
+
+///
+/// 规则对应的中文行: 同层中 id 相同的行, 否则取三种策略类型均相同的行
+fn zh_cn_of<'a>(
+    rule: &feature_config_layer_rule_ids::Model,
+    rows: &'a [feature_config_layer_rule_zh_cn::Model],
+) -> Option<&'a feature_config_layer_rule_zh_cn::Model> {
+    rows.iter().find(|m| m.id == rule.id).or_else(|| {
+        rows.iter().find(|m| {
+            m.type_strategy_actual == rule.type_strategy_actual
+                && m.type_strategy_expect == rule.type_strategy_expect
+                && m.type_strategy_demotion == rule.type_strategy_demotion
+        })
+    })
+}
+
+async fn existing_config_ids(
+    db: &DatabaseConnection,
+    ids: Vec<i32>,
+) -> Result<BTreeSet<i32>, DError> {
+    if ids.is_empty() {
+        return Ok(BTreeSet::new());
+    }
+    let existing = feature_config::Entity::find()
+        .select_only()
+        .column(feature_config::Column::Id)
+        .filter(feature_config::Column::Id.is_in(ids))
+        .filter(not_deleted(feature_config::Column::IsDelete))
+        .into_tuple::<i32>()
+        .all(db)
+        .await?;
+    Ok(existing.into_iter().collect())
+}
+
+///
+/// 按规则中的顺序加载未删除的 feature_config
+async fn load_configs(
+    db: &DatabaseConnection,
+    ids: &[i32],
+) -> Result<Vec<feature_config::Model>, DError> {
+    if ids.is_empty() {
+        return Ok(vec![]);
+    }
+    let rows = feature_config::Entity::find()
+        .select_only()
+        .columns(
+            feature_config::Column::iter().filter(|c| !matches!(c, feature_config::Column::Labels)),
+        )
+        .expr_as(Expr::cust(LABELS_SELECT_EXPR), "labels")
+        .filter(feature_config::Column::Id.is_in(ids.to_vec()))
+        .filter(not_deleted(feature_config::Column::IsDelete))
+        .into_model::<feature_config::Model>()
+        .all(db)
+        .await?;
+    let mut by_id: BTreeMap<i32, feature_config::Model> =
+        rows.into_iter().map(|m| (m.id, m)).collect();
+    Ok(ids.iter().filter_map(|id| by_id.remove(id)).collect())
+}
@@ -0,0 +1,274 @@
+mod approval;
+mod artifactory;
+mod conflict;
+mod feature_config_history;
+mod feature_tag;
+mod label_tree;
+mod layer_rule;
+mod polling;
+mod solution_history;
+mod solution_search;
+mod workflow;
+use sea_orm::{ColumnTrait, Condition};
+use seaography::async_graphql::dynamic::{
+    Field, FieldFuture, FieldValue, Object, ResolverContext, TypeRef,
+};
+use seaography::async_graphql::{Error, Value};
+use seaography::Builder;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+use crate::util::{json_diff, JsonChange};
+
+const LABEL_TABLES: &[&str] = &[
+    "feature_config_label_lv1",
+    "feature_config_label_lv2",
+    "feature_config_label_lv3",
+    "feature_config_label_lv4",
+];
+const APPROVAL_TABLES: &[&str] = &["fc_cfg_approval_flow", "st_wf_approval_flow"];
+const WORKFLOW_TABLES: &[&str] = &[
+    "solution_workflow",
+    "st_wf_sol_pack",
+    "st_wf_solution",
+    "st_workflow",
+];
+
+/// 自定义根字段 -> 读写的表, 新增自定义查询/变更时需同步登记
+pub const ROOT_FIELD_TABLES: &[(&str, &[&str])] = &[
+    ("approve", APPROVAL_TABLES),
+    ("reject", APPROVAL_TABLES),
+    ("latestCompatibleArtifactory", &["artifactory"]),
+    (
+        "featureConflictAnalysis",
+        &["feature_config", "feature_config_conflict"],
+    ),
+    (
+        "featureConfigAsOf",
+        &["feature_config", "feature_config_history"],
+    ),
+    (
+        "featureConfigDiffBetween",
+        &["feature_config", "feature_config_history"],
+    ),
+    (
+        "evaluateFeatureTags",
+        &["feature_config", "feature_tag_config"],
+    ),
+    (
+        "labelTree",
+        &[
+            "feature_config",
+            "feature_config_label_lv1",
+            "feature_config_label_lv2",
+            "feature_config_label_lv3",
+            "feature_config_label_lv4",
+        ],
+    ),
+    ("validateLabelPaths", LABEL_TABLES),
+    (
+        "resolveLayerRules",
+        &[
+            "feature_config",
+            "feature_config_layer_rule_ids",
+            "feature_config_layer_rule_zh_cn",
+            "feature_config_layers",
+            "feature_config_layers_sol_all",
+        ],
+    ),
+    (
+        "pollingAttemptExplain",
+        &["st_polling_log", "st_polling_task"],
+    ),
+    ("solutionTimeline", &["solution_history"]),
+    ("solutionRevisionDiff", &["solution_history"]),
+    (
+        "searchSolutions",
+        &["solution", "solution_label", "solution_way"],
+    ),
+    ("advanceWorkflow", WORKFLOW_TABLES),
+    ("rollbackWorkflow", WORKFLOW_TABLES),
+];
+
+///
+/// 注册自定义查询/变更(实体自动生成的接口之外, 需要服务端计算/聚合或校验的接口)
+pub fn register_custom_queries(mut builder: Builder) -> Builder {
+    builder.outputs.push(json_change_object());
+    builder.outputs.push(field_change_object());
+    approval::register(&mut builder);
+    artifactory::register(&mut builder);
+    conflict::register(&mut builder);
+    feature_config_history::register(&mut builder);
+    feature_tag::register(&mut builder);
+    label_tree::register(&mut builder);
+    layer_rule::register(&mut builder);
+    polling::register(&mut builder);
+    solution_history::register(&mut builder);
+    solution_search::register(&mut builder);
+    workflow::register(&mut builder);
+    builder
+}
+
+/// 以 `serde_json::Value` 为父值的输出对象字段
+///
+/// 结果结构体序列化(camelCase)后整体作为父值, 字段按名称从中取值:
+/// 标量/标量列表/Json 用 `Scalar`, 嵌套对象或对象列表用 `Object`
+pub enum JsonField {
+    Scalar(&'static str, TypeRef),
+    Object(&'static str, TypeRef),
+}
+
+///
+/// 构建以 json 为父值的动态输出对象
+pub fn json_object(name: &str, fields: Vec<JsonField>) -> Object {
+    fields.into_iter().fold(Object::new(name), |obj, f| {
+        let (field_name, ty, nested) = match f {
+            JsonField::Scalar(n, ty) => (n, ty, false),
+            JsonField::Object(n, ty) => (n, ty, true),
+        };
+        obj.field(Field::new(field_name, ty, move |ctx| {
+            FieldFuture::new(async move {
+                let parent = ctx.parent_value.try_downcast_ref::<serde_json::Value>()?;
+                let v = match parent.get(field_name) {
+                    None | Some(serde_json::Value::Null) => return Ok(None),
+                    Some(v) => v,
+                };
+                if !nested {
+                    return Ok(Some(FieldValue::value(Value::from_json(v.clone())?)));
+                }
+                Ok(Some(json_field_value(v.clone())))
+            })
+        }))
+    })
+}
+
+fn json_field_value(v: serde_json::Value) -> FieldValue<'static> {
+    match v {
+        serde_json::Value::Array(arr) => FieldValue::list(arr.into_iter().map(json_field_value)),
+        other => FieldValue::owned_any(other),
+    }
+}
+
+///
+/// 将查询结果转换为 [`json_object`] 可解析的父值
+pub fn json_output<T: Serialize>(v: &T) -> Result<Option<FieldValue<'static>>, Error> {
+    Ok(Some(json_field_value(serde_json::to_value(v)?)))
+}
+
+///
+/// 通用的 json 结构化差异输出对象, 对应 [`crate::util::JsonChange`]
+fn json_change_object() -> Object {
+    json_object(
+        "JsonChange",
+        vec![
+            JsonField::Scalar("path", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("op", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("before", TypeRef::named("Json")),
+            JsonField::Scalar("after", TypeRef::named("Json")),
+        ],
+    )
+}
+
+/// 单个字段的变更, json 列额外给出结构化差异
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub json_diff: Option<Vec<JsonChange>>,
+}
+
+fn field_change_object() -> Object {
+    json_object(
+        "FieldChange",
+        vec![
+            JsonField::Scalar("field", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("before", TypeRef::named("Json")),
+            JsonField::Scalar("after", TypeRef::named("Json")),
+            JsonField::Object("jsonDiff", TypeRef::named_list_nn("JsonChange")),
+        ],
+    )
+}
+
+///
+/// 比较两条序列化后的记录, 返回有变化的字段; `json_fields` 中的列给出结构化差异
+pub fn diff_fields(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+    ignored: &[&str],
+    json_fields: &[&str],
+) -> Vec<FieldChange> {
+    let keys: BTreeSet<&String> = before
+        .as_object()
+        .into_iter()
+        .chain(after.as_object())
+        .flat_map(|m| m.keys())
+        .filter(|k| !ignored.contains(&k.as_str()))
+        .collect();
+    keys.into_iter()
+        .filter_map(|k| {
+            let (b, a) = (before.get(k), after.get(k));
+            if b == a {
+                return None;
+            }
+            let null = serde_json::Value::Null;
+            let structural = json_fields
+                .contains(&k.as_str())
+                .then(|| json_diff(b.unwrap_or(&null), a.unwrap_or(&null)));
+            Some(FieldChange {
+                field: k.clone(),
+                before: b.cloned(),
+                after: a.cloned(),
+                json_diff: structural,
+            })
+        })
+        .collect()
+}
+
+///
+/// 解析 json 中的 id 列表, 兼容单个 id 及字符串形式的数字
+pub fn parse_ids(v: &serde_json::Value) -> Vec<i32> {
+    let one = |v: &serde_json::Value| match v {
+        serde_json::Value::Number(n) => n.as_i64().map(|n| n as i32),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    };
+    match v {
+        serde_json::Value::Array(arr) => arr.iter().filter_map(one).collect(),
+        other => one(other).into_iter().collect(),
+    }
+}
+
+///
+/// 转义 LIKE 模式中的 `\`、`%`、`_`(postgres 默认以 `\` 为转义符)
+pub fn escape_like(v: &str) -> String {
+    v.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+///
+/// 读取 bigint 主键参数: 参数类型为 `ID`(Int 只有 32 位), 兼容字符串及整数字面量
+pub fn id_arg(ctx: &ResolverContext, name: &str) -> Result<i64, Error> {
+    let v = ctx.args.try_get(name)?;
+    match v.string() {
+        Ok(s) => s
+            .trim()
+            .parse()
+            .map_err(|_| Error::new(format!("invalid {} <{}>", name, s))),
+        Err(_) => Ok(v.i64()?),
+    }
+}
+
+///
+/// bigint 主键按字符串输出(`ID`), 避免超出 Int 及 js number 的精度
+pub fn serialize_id<S: serde::Serializer>(id: &i64, s: S) -> Result<S::Ok, S::Error> {
+    s.collect_str(id)
+}
+
+///
+/// `is_delete` 为空或 false
+pub fn not_deleted<C: ColumnTrait>(col: C) -> Condition {
+    Condition::any().add(col.is_null()).add(col.eq(false))
+}
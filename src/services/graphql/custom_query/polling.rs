@@ -0,0 +1,97 @@
+use entity_graphql::{st_polling_log, st_polling_task};
+use sea_orm::{DatabaseConnection, EntityTrait};
+use seaography::async_graphql::dynamic::{Field, FieldFuture, InputValue, TypeRef};
+use seaography::Builder;
+use serde::Serialize;
+
+use super::{id_arg, json_object, json_output, serialize_id, JsonField};
+use crate::config::polling::TaskStatusCodes;
+use crate::error::{DError, LogicErr};
+use crate::polling::rule::{self, RuleCheck};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AttemptExplain {
+    #[serde(serialize_with = "serialize_id")]
+    log_id: i64,
+    #[serde(serialize_with = "serialize_id")]
+    task_id: i64,
+    retry_count: i64,
+    status: i16,
+    status_name: String,
+    satisfied: bool,
+    checks: Vec<RuleCheck>,
+}
+
+pub fn register(builder: &mut Builder) {
+    builder.outputs.push(json_object(
+        "PollingRuleCheck",
+        vec![
+            JsonField::Scalar("rule", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("passed", TypeRef::named_nn(TypeRef::BOOLEAN)),
+            JsonField::Scalar("fatal", TypeRef::named_nn(TypeRef::BOOLEAN)),
+            JsonField::Scalar("detail", TypeRef::named_nn(TypeRef::STRING)),
+        ],
+    ));
+    builder.outputs.push(json_object(
+        "PollingAttemptExplain",
+        vec![
+            JsonField::Scalar("logId", TypeRef::named_nn(TypeRef::ID)),
+            JsonField::Scalar("taskId", TypeRef::named_nn(TypeRef::ID)),
+            JsonField::Scalar("retryCount", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("status", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("statusName", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("satisfied", TypeRef::named_nn(TypeRef::BOOLEAN)),
+            JsonField::Object("checks", TypeRef::named_nn_list_nn("PollingRuleCheck")),
+        ],
+    ));
+    builder.queries.push(polling_attempt_explain());
+}
+
+///
+/// 按任务当前的完成条件重新判定一次请求(st_polling_log), 列出每条规则的检查结果
+fn polling_attempt_explain() -> Field {
+    Field::new(
+        "pollingAttemptExplain",
+        TypeRef::named_nn("PollingAttemptExplain"),
+        |ctx| {
+            FieldFuture::new(async move {
+                let db = ctx.data::<DatabaseConnection>()?;
+                let log_id = id_arg(&ctx, "logId")?;
+                let log = st_polling_log::Entity::find_by_id(log_id)
+                    .one(db)
+                    .await
+                    .map_err(DError::from)?
+                    .ok_or_else(|| {
+                        DError::Custom(LogicErr::NotFound(format!("st_polling_log <{}>", log_id)))
+                    })?;
+                let task = st_polling_task::Entity::find_by_id(log.task_id)
+                    .one(db)
+                    .await
+                    .map_err(DError::from)?
+                    .ok_or_else(|| {
+                        DError::Custom(LogicErr::NotFound(format!(
+                            "st_polling_task <{}>",
+                            log.task_id
+                        )))
+                    })?;
+                let evaluation = rule::evaluate(&task, &log);
+                // 请求级 data, 随配置热更新
+                let codes = ctx
+                    .data_opt::<TaskStatusCodes>()
+                    .copied()
+                    .unwrap_or_default();
+                json_output(&AttemptExplain {
+                    log_id: log.id,
+                    task_id: task.id,
+                    retry_count: log.retry_count,
+                    status: evaluation.status.code(&codes),
+                    status_name: format!("{:?}", evaluation.status),
+                    satisfied: evaluation.satisfied(),
+                    checks: evaluation.checks,
+                })
+            })
+        },
+    )
+    .argument(InputValue::new("logId", TypeRef::named_nn(TypeRef::ID)))
+}
@@ -0,0 +1,160 @@
+use entity_graphql::solution_history;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder};
+use seaography::async_graphql::dynamic::{Field, FieldFuture, InputValue, TypeRef};
+use seaography::Builder;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{diff_fields, json_object, json_output, FieldChange, JsonField};
+use crate::error::{DError, LogicErr};
+
+/// 不参与比较的元数据列
+const IGNORED_FIELDS: &[&str] = &[
+    "id",
+    "action",
+    "create_time",
+    "editer_time",
+    "create_avatar_name",
+    "create_avatar_id",
+];
+
+/// 需要给出结构化差异的 json 列
+const JSON_FIELDS: &[&str] = &["tag", "solution_control_group", "tag_base_editer"];
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TimelineEntry {
+    id: i32,
+    action: String,
+    editer_time: Option<String>,
+    create_avatar_name: Option<String>,
+    create_avatar_id: Option<i32>,
+    // 相对上一修订的变更, 第一条修订为空
+    changes: Vec<FieldChange>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RevisionDiff {
+    solution_id: String,
+    from_id: i32,
+    to_id: i32,
+    changes: Vec<FieldChange>,
+}
+
+pub fn register(builder: &mut Builder) {
+    builder.outputs.push(json_object(
+        "SolutionTimelineEntry",
+        vec![
+            JsonField::Scalar("id", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("action", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("editerTime", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("createAvatarName", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("createAvatarId", TypeRef::named(TypeRef::INT)),
+            JsonField::Object("changes", TypeRef::named_nn_list_nn("FieldChange")),
+        ],
+    ));
+    builder.outputs.push(json_object(
+        "SolutionRevisionDiff",
+        vec![
+            JsonField::Scalar("solutionId", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("fromId", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("toId", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Object("changes", TypeRef::named_nn_list_nn("FieldChange")),
+        ],
+    ));
+    builder.queries.push(solution_timeline());
+    builder.queries.push(solution_revision_diff());
+}
+
+///
+/// 某个 solution 的修订时间线(按历史记录写入顺序), 每条给出相对上一修订的字段变更
+fn solution_timeline() -> Field {
+    Field::new(
+        "solutionTimeline",
+        TypeRef::named_nn_list_nn("SolutionTimelineEntry"),
+        |ctx| {
+            FieldFuture::new(async move {
+                let db = ctx.data::<DatabaseConnection>()?;
+                let solution_id = ctx.args.try_get("solutionId")?.string()?.to_owned();
+                let revisions = solution_history::Entity::find()
+                    .filter(solution_history::Column::SolutionId.eq(solution_id))
+                    .order_by_asc(solution_history::Column::Id)
+                    .all(db)
+                    .await
+                    .map_err(DError::from)?;
+                let mut timeline = vec![];
+                let mut prev: Option<Value> = None;
+                for rev in revisions {
+                    let cur = serde_json::to_value(&rev)?;
+                    timeline.push(TimelineEntry {
+                        id: rev.id,
+                        action: rev.action,
+                        editer_time: rev.editer_time.map(|t| t.to_rfc3339()),
+                        create_avatar_name: rev.create_avatar_name,
+                        create_avatar_id: rev.create_avatar_id,
+                        changes: prev
+                            .as_ref()
+                            .map(|p| diff_fields(p, &cur, IGNORED_FIELDS, JSON_FIELDS))
+                            .unwrap_or_default(),
+                    });
+                    prev = Some(cur);
+                }
+                json_output(&timeline)
+            })
+        },
+    )
+    .argument(InputValue::new(
+        "solutionId",
+        TypeRef::named_nn(TypeRef::STRING),
+    ))
+}
+
+///
+/// 比较同一 solution 的任意两个历史修订
+fn solution_revision_diff() -> Field {
+    Field::new(
+        "solutionRevisionDiff",
+        TypeRef::named_nn("SolutionRevisionDiff"),
+        |ctx| {
+            FieldFuture::new(async move {
+                let db = ctx.data::<DatabaseConnection>()?;
+                let from_id = ctx.args.try_get("fromId")?.i64()? as i32;
+                let to_id = ctx.args.try_get("toId")?.i64()? as i32;
+                let from = find_revision(db, from_id).await?;
+                let to = find_revision(db, to_id).await?;
+                if from.solution_id != to.solution_id {
+                    return Err(DError::Custom(LogicErr::ParamsError(format!(
+                        "revisions belong to different solutions <{}> <{}>",
+                        from.solution_id, to.solution_id
+                    )))
+                    .into());
+                }
+                let changes = diff_fields(
+                    &serde_json::to_value(&from)?,
+                    &serde_json::to_value(&to)?,
+                    IGNORED_FIELDS,
+                    JSON_FIELDS,
+                );
+                json_output(&RevisionDiff {
+                    solution_id: to.solution_id,
+                    from_id,
+                    to_id,
+                    changes,
+                })
+            })
+        },
+    )
+    .argument(InputValue::new("fromId", TypeRef::named_nn(TypeRef::INT)))
+    .argument(InputValue::new("toId", TypeRef::named_nn(TypeRef::INT)))
+}
+
+async fn find_revision(
+    db: &DatabaseConnection,
+    id: i32,
+) -> Result<solution_history::Model, DError> {
+    solution_history::Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DError::Custom(LogicErr::NotFound(format!("solution_history <{}>", id))))
+}
@@ -0,0 +1,361 @@
+use entity_graphql::sea_orm_active_enums::Esolutionstate;
+use sea_orm::{
+    ActiveEnum, DatabaseConnection, DbBackend, FromQueryResult, Statement, Value as DbValue,
+};
+use seaography::async_graphql::dynamic::{
+    Field, FieldFuture, InputValue, ResolverContext, TypeRef,
+};
+use seaography::async_graphql::Error;
+use seaography::Builder;
+use serde::Serialize;
+
+use super::{escape_like, json_object, json_output, JsonField};
+use crate::config::graphql::GraphqlSetting;
+use crate::error::{DError, LogicErr};
+use crate::feature_tag::service::parse_platform;
+
+const DEFAULT_LIMIT: u64 = 20;
+const MAX_LIMIT: u64 = 100;
+// 深分页需扫描并丢弃前面全部结果
+const MAX_OFFSET: u64 = 10_000;
+const HIGHLIGHT_START: &str = "<mark>";
+const HIGHLIGHT_STOP: &str = "</mark>";
+// 关闭子串兜底时不引用 pg_trgm 的函数, 允许未安装该插件
+const TRGM_RANK: [&str; 2] = ["0::real", "word_similarity($1, d.body)"];
+// 兜底匹配时片段前后保留的字符数
+const SNIPPET_CONTEXT: usize = 30;
+
+/// 检索语句, `{cfg}` 为 text search configuration, `{trgm}`/`{trgm_rank}` 为子串兜底的开关与相似度
+///
+/// 候选方案按表分别以 migrations/0003_solution_search.sql 中的索引表达式匹配后 UNION,
+/// 避免跨 LEFT JOIN 的 OR 使索引失效; 表达式需与索引保持一致.
+/// 权重: 摘要 A, 描述 B, 方案方式背景 C, 备注 D.
+/// ts_headline 的输入先做 html 转义, 片段中只有 `<mark></mark>` 为标签
+const SEARCH_SQL: &str = r#"
+WITH matched AS (
+    SELECT s.id FROM solution s
+    WHERE (
+        setweight(to_tsvector('{cfg}', coalesce(s.solution_desc_abbreviated, '')), 'A')
+        || setweight(to_tsvector('{cfg}', coalesce(s.solution_desc_text, '')), 'B')
+        || setweight(to_tsvector('{cfg}', coalesce(s.notes, '')), 'D')
+    ) @@ websearch_to_tsquery('{cfg}', $1)
+    UNION
+    SELECT s.id FROM solution s
+    WHERE {trgm}
+      AND (
+        coalesce(s.solution_desc_abbreviated, '') || ' ' || coalesce(s.solution_desc_text, '')
+        || ' ' || coalesce(s.notes, '')
+      ) ILIKE $2
+    UNION
+    SELECT s.id FROM solution_way w
+    JOIN solution s ON s.platform = w.platform AND s.way_type = w.way_type AND s.way = w.way
+    WHERE w.is_delete IS NOT TRUE
+      AND setweight(to_tsvector('{cfg}', coalesce(w.background, '')), 'C')
+          @@ websearch_to_tsquery('{cfg}', $1)
+    UNION
+    SELECT s.id FROM solution_way w
+    JOIN solution s ON s.platform = w.platform AND s.way_type = w.way_type AND s.way = w.way
+    WHERE w.is_delete IS NOT TRUE AND {trgm} AND coalesce(w.background, '') ILIKE $2
+)
+SELECT s.id, s.solution_id, s.platform::text AS platform, s.solution_state::text AS solution_state,
+       l.label, s.solution_desc_abbreviated,
+       ts_rank(d.vec, q.query)
+         + {trgm_rank} AS rank,
+       ts_headline('{cfg}', e.abbreviated, q.query, $7) AS abbreviated_snippet,
+       ts_headline('{cfg}', e.desc_text, q.query, $7) AS desc_snippet,
+       ts_headline('{cfg}', e.background, q.query, $7) AS background_snippet,
+       ts_headline('{cfg}', e.notes, q.query, $7) AS notes_snippet,
+       s.solution_desc_text, w.background, s.notes
+FROM matched m
+JOIN solution s ON s.id = m.id
+LEFT JOIN solution_label l ON l.id = s.solution_label_id
+LEFT JOIN solution_way w
+       ON w.platform = s.platform AND w.way_type = s.way_type AND w.way = s.way
+      AND w.is_delete IS NOT TRUE
+CROSS JOIN websearch_to_tsquery('{cfg}', $1) AS q(query)
+CROSS JOIN LATERAL (
+    SELECT setweight(to_tsvector('{cfg}', coalesce(s.solution_desc_abbreviated, '')), 'A')
+           || setweight(to_tsvector('{cfg}', coalesce(s.solution_desc_text, '')), 'B')
+           || setweight(to_tsvector('{cfg}', coalesce(s.notes, '')), 'D')
+           || setweight(to_tsvector('{cfg}', coalesce(w.background, '')), 'C') AS vec,
+           coalesce(s.solution_desc_abbreviated, '') || ' ' || coalesce(s.solution_desc_text, '')
+           || ' ' || coalesce(s.notes, '') || ' ' || coalesce(w.background, '') AS body
+) d
+CROSS JOIN LATERAL (
+    SELECT {esc:s.solution_desc_abbreviated} AS abbreviated,
+           {esc:s.solution_desc_text} AS desc_text,
+           {esc:w.background} AS background,
+           {esc:s.notes} AS notes
+) e
+WHERE s.is_delete IS NOT TRUE
+  AND ($3::text IS NULL OR s.platform::text = $3)
+  AND ($4::text IS NULL OR s.solution_state::text = $4)
+  AND ($5::text IS NULL OR l.label = $5)
+ORDER BY rank DESC, s.id DESC
+LIMIT $6 OFFSET $8
+"#;
+
+/// 片段原文的 html 转义, 与 [`escape_html`] 一致
+const ESCAPE_HTML_SQL: &str =
+    "replace(replace(replace(replace(coalesce({col}, ''), '&', '&amp;'), \
+                               '<', '&lt;'), '>', '&gt;'), '\"', '&quot;')";
+
+#[derive(Debug, FromQueryResult)]
+struct SearchRow {
+    id: i32,
+    solution_id: String,
+    platform: String,
+    solution_state: String,
+    label: Option<String>,
+    solution_desc_abbreviated: Option<String>,
+    rank: f32,
+    abbreviated_snippet: String,
+    desc_snippet: String,
+    background_snippet: String,
+    notes_snippet: String,
+    solution_desc_text: Option<String>,
+    background: Option<String>,
+    notes: Option<String>,
+}
+
+/// 命中字段的高亮片段, 原文已做 html 转义, 命中部分以 `<mark></mark>` 包裹
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchSnippet {
+    field: &'static str,
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchHit {
+    id: i32,
+    solution_id: String,
+    platform: String,
+    solution_state: String,
+    label: Option<String>,
+    solution_desc_abbreviated: Option<String>,
+    rank: f32,
+    snippets: Vec<SearchSnippet>,
+}
+
+pub fn register(builder: &mut Builder) {
+    builder.outputs.push(json_object(
+        "SolutionSearchSnippet",
+        vec![
+            JsonField::Scalar("field", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("text", TypeRef::named_nn(TypeRef::STRING)),
+        ],
+    ));
+    builder.outputs.push(json_object(
+        "SolutionSearchHit",
+        vec![
+            JsonField::Scalar("id", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("solutionId", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("platform", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("solutionState", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("label", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("solutionDescAbbreviated", TypeRef::named(TypeRef::STRING)),
+            JsonField::Scalar("rank", TypeRef::named_nn(TypeRef::FLOAT)),
+            JsonField::Object(
+                "snippets",
+                TypeRef::named_nn_list_nn("SolutionSearchSnippet"),
+            ),
+        ],
+    ));
+    builder.queries.push(search_solutions());
+}
+
+///
+/// 按相关度检索方案描述/摘要/备注及方案方式背景, 支持 platform/state/label 过滤
+fn search_solutions() -> Field {
+    Field::new(
+        "searchSolutions",
+        TypeRef::named_nn_list_nn("SolutionSearchHit"),
+        |ctx| {
+            FieldFuture::new(async move {
+                let db = ctx.data::<DatabaseConnection>()?;
+                let setting = ctx.data::<GraphqlSetting>()?;
+                let query = ctx.args.try_get("query")?.string()?.trim().to_owned();
+                if query.is_empty() {
+                    return Err(params_err("query is empty".to_owned()).into());
+                }
+                // 与其它接口一致, 平台名忽略大小写且接受 android
+                let platform = match ctx.args.get("platform") {
+                    Some(v) => Some(parse_platform(v.string()?)?.to_value()),
+                    None => None,
+                };
+                let state = enum_arg::<Esolutionstate>(&ctx, "state")?;
+                let label = ctx
+                    .args
+                    .get("label")
+                    .map(|v| v.string().map(str::to_owned))
+                    .transpose()?;
+                let limit = match ctx.args.get("limit") {
+                    Some(v) => v.u64()?.clamp(1, MAX_LIMIT),
+                    None => DEFAULT_LIMIT,
+                };
+                let offset = match ctx.args.get("offset") {
+                    Some(v) => v.u64()?.min(MAX_OFFSET),
+                    None => 0,
+                };
+                let rows =
+                    search(db, setting, &query, [platform, state, label], limit, offset).await?;
+                let hits: Vec<SearchHit> = rows.into_iter().map(|r| to_hit(r, &query)).collect();
+                json_output(&hits)
+            })
+        },
+    )
+    .argument(InputValue::new("query", TypeRef::named_nn(TypeRef::STRING)))
+    .argument(InputValue::new("platform", TypeRef::named(TypeRef::STRING)))
+    .argument(InputValue::new("state", TypeRef::named(TypeRef::STRING)))
+    .argument(InputValue::new("label", TypeRef::named(TypeRef::STRING)))
+    .argument(InputValue::new("limit", TypeRef::named(TypeRef::INT)))
+    .argument(InputValue::new("offset", TypeRef::named(TypeRef::INT)))
+}
+
+async fn search(
+    db: &DatabaseConnection,
+    setting: &GraphqlSetting,
+    query: &str,
+    [platform, state, label]: [Option<String>; 3],
+    limit: u64,
+    offset: u64,
+) -> Result<Vec<SearchRow>, DError> {
+    // configuration 名拼入语句(表达式索引要求常量), 取值已在配置校验时限定为标识符
+    let cfg = &setting.search_ts_config;
+    let mut sql = SEARCH_SQL.to_owned();
+    for col in [
+        "s.solution_desc_abbreviated",
+        "s.solution_desc_text",
+        "w.background",
+        "s.notes",
+    ] {
+        sql = sql.replace(
+            &format!("{{esc:{}}}", col),
+            &ESCAPE_HTML_SQL.replace("{col}", col),
+        );
+    }
+    let sql = sql
+        .replace("{cfg}", cfg)
+        .replace("{trgm_rank}", TRGM_RANK[setting.search_trgm as usize])
+        .replace("{trgm}", if setting.search_trgm { "TRUE" } else { "FALSE" });
+    let headline_opts = format!(
+        "StartSel={},StopSel={},MaxFragments=2,MaxWords=30,MinWords=10",
+        HIGHLIGHT_START, HIGHLIGHT_STOP
+    );
+    let values: [DbValue; 8] = [
+        query.into(),
+        format!("%{}%", escape_like(query)).into(),
+        platform.into(),
+        state.into(),
+        label.into(),
+        (limit as i64).into(),
+        headline_opts.into(),
+        (offset as i64).into(),
+    ];
+    let rows = SearchRow::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        sql,
+        values,
+    ))
+    .all(db)
+    .await?;
+    Ok(rows)
+}
+
+///
+/// 组装命中结果: 取含高亮的 ts_headline 片段, 分词未命中(子串兜底)时在原文中定位关键词生成片段
+fn to_hit(row: SearchRow, query: &str) -> SearchHit {
+    let fields = [
+        (
+            "solutionDescAbbreviated",
+            row.abbreviated_snippet,
+            row.solution_desc_abbreviated.clone(),
+        ),
+        ("solutionDescText", row.desc_snippet, row.solution_desc_text),
+        ("background", row.background_snippet, row.background),
+        ("notes", row.notes_snippet, row.notes),
+    ];
+    let mut snippets: Vec<SearchSnippet> = fields
+        .iter()
+        .filter(|(_, headline, _)| headline.contains(HIGHLIGHT_START))
+        .map(|(field, headline, _)| SearchSnippet {
+            field: *field,
+            text: headline.clone(),
+        })
+        .collect();
+    if snippets.is_empty() {
+        snippets = fields
+            .iter()
+            .filter_map(|(field, _, text)| {
+                substring_snippet(text.as_deref()?, query).map(|text| SearchSnippet {
+                    field: *field,
+                    text,
+                })
+            })
+            .collect();
+    }
+    SearchHit {
+        id: row.id,
+        solution_id: row.solution_id,
+        platform: row.platform,
+        solution_state: row.solution_state,
+        label: row.label,
+        solution_desc_abbreviated: row.solution_desc_abbreviated,
+        rank: row.rank,
+        snippets,
+    }
+}
+
+///
+/// 大小写不敏感定位 `query` 首次出现的位置, 截取前后 [`SNIPPET_CONTEXT`] 个字符并高亮
+fn substring_snippet(text: &str, query: &str) -> Option<String> {
+    // 两侧逐字符取小写的首个字符, 保持与原文下标一一对应(如 'İ' 的小写为两个字符)
+    let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let chars: Vec<char> = text.chars().collect();
+    let needle: Vec<char> = query.chars().map(fold).collect();
+    let lower: Vec<char> = chars.iter().copied().map(fold).collect();
+    let start = lower.windows(needle.len()).position(|w| w == needle)?;
+    let end = start + needle.len();
+    let from = start.saturating_sub(SNIPPET_CONTEXT);
+    let to = (end + SNIPPET_CONTEXT).min(chars.len());
+    let collect = |r: std::ops::Range<usize>| chars[r].iter().collect::<String>();
+    Some(format!(
+        "{}{}{}{}{}{}{}",
+        if from > 0 { "..." } else { "" },
+        escape_html(&collect(from..start)),
+        HIGHLIGHT_START,
+        escape_html(&collect(start..end)),
+        HIGHLIGHT_STOP,
+        escape_html(&collect(end..to)),
+        if to < chars.len() { "..." } else { "" },
+    ))
+}
+
+///
+/// 片段原文的 html 转义, 使高亮标签之外不含可解析的标记
+fn escape_html(v: &str) -> String {
+    v.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+///
+/// 可选的枚举参数, 校验取值后以字符串参与比较
+fn enum_arg<T>(ctx: &ResolverContext, name: &str) -> Result<Option<String>, Error>
+where
+    T: ActiveEnum<Value = String>,
+{
+    let Some(v) = ctx.args.get(name) else {
+        return Ok(None);
+    };
+    let v = v.string()?.to_owned();
+    T::try_from_value(&v).map_err(|_| params_err(format!("{} <{}>", name, v)))?;
+    Ok(Some(v))
+}
+
+fn params_err(msg: String) -> DError {
+    DError::Custom(LogicErr::ParamsError(msg))
+}
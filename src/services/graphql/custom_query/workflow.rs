@@ -0,0 +1,61 @@
+use sea_orm::DatabaseConnection;
+use seaography::async_graphql::dynamic::{Field, FieldFuture, InputValue, TypeRef};
+use seaography::Builder;
+
+use super::{json_object, json_output, JsonField};
+use crate::config::graphql::GraphqlSetting;
+use crate::error::{DError, LogicErr};
+use crate::workflow::{service, Direction, WorkflowTarget};
+
+pub fn register(builder: &mut Builder) {
+    builder.outputs.push(json_object(
+        "WorkflowTransition",
+        vec![
+            JsonField::Scalar("entity", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("id", TypeRef::named_nn(TypeRef::INT)),
+            JsonField::Scalar("direction", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("fromState", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("toState", TypeRef::named_nn(TypeRef::STRING)),
+            JsonField::Scalar("curLayer", TypeRef::named(TypeRef::INT)),
+            JsonField::Scalar("curLayerStep", TypeRef::named(TypeRef::INT)),
+        ],
+    ));
+    builder
+        .mutations
+        .push(transition_field("advanceWorkflow", Direction::Advance));
+    builder
+        .mutations
+        .push(transition_field("rollbackWorkflow", Direction::Rollback));
+}
+
+///
+/// 按状态机推进/回退 st_workflow、solution_workflow、st_wf_sol_pack 的状态
+///
+/// `entity` 为表名; 未指定 `to` 时取默认目标状态, 非法流转返回 IllegalTransition
+fn transition_field(name: &str, direction: Direction) -> Field {
+    Field::new(name, TypeRef::named_nn("WorkflowTransition"), move |ctx| {
+        FieldFuture::new(async move {
+            let db = ctx.data::<DatabaseConnection>()?;
+            // 请求级 data, 状态机随配置热更新
+            let machines = ctx.data::<GraphqlSetting>()?.workflow_machines.clone();
+            let entity = ctx.args.try_get("entity")?.string()?;
+            let target = WorkflowTarget::from_name(entity).ok_or_else(|| {
+                DError::Custom(LogicErr::ParamsError(format!("entity <{}>", entity)))
+            })?;
+            let id = ctx.args.try_get("id")?.i64()?;
+            // 显式传入 null 等同于未指定
+            let to = match ctx.args.get("to").filter(|v| !v.is_null()) {
+                Some(v) => Some(v.string()?),
+                None => None,
+            };
+            let result = service::transition(db, &machines, target, id, to, direction).await?;
+            json_output(&result)
+        })
+    })
+    .argument(InputValue::new(
+        "entity",
+        TypeRef::named_nn(TypeRef::STRING),
+    ))
+    .argument(InputValue::new("id", TypeRef::named_nn(TypeRef::INT)))
+    .argument(InputValue::new("to", TypeRef::named(TypeRef::STRING)))
+}
@@ -0,0 +1,155 @@
+use seaography::async_graphql::dynamic::ResolverContext;
+use seaography::async_graphql::parser::types::{ExecutableDocument, OperationDefinition};
+use seaography::async_graphql::Error;
+use seaography::{GuardAction, LifecycleHooksInterface, OperationType};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::custom_query::ROOT_FIELD_TABLES;
+use super::operation::root_fields;
+use crate::config::auth::AuthSetting;
+use crate::error::{DError, LogicErr};
+use crate::middleware::auth::AuthClaims;
+use crate::util::camel_to_snake;
+
+/// 按角色限制实体/字段/自定义根字段访问的策略, 实体/字段 key 使用表名/列名,
+/// 根字段 key 使用 graphql 字段名
+///
+/// ```json
+/// {
+///     "entities": { "st_polling_task": ["admin", "ops"] },
+///     "fields": { "st_polling_task.headers": ["admin"] },
+///     "root_fields": { "advanceWorkflow": ["admin"] }
+/// }
+/// ```
+///
+/// 未出现在策略中的实体/字段不做限制; 命中时要求持有任一角色.
+/// 自定义根字段(非实体自动生成)另需满足其读写的各表的实体限制, 见 [`ROOT_FIELD_TABLES`]
+#[derive(Debug, Default, Deserialize)]
+pub struct AuthPolicy {
+    #[serde(default)]
+    pub entities: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub fields: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub root_fields: HashMap<String, Vec<String>>,
+}
+
+impl AuthPolicy {
+    ///
+    /// 启动时读取策略文件, 未配置时不做限制
+    pub fn from_setting(setting: &AuthSetting) -> Result<Self, Box<dyn std::error::Error>> {
+        let Some(f) = &setting.policy_file else {
+            return Ok(AuthPolicy::default());
+        };
+        let js_cont: String = std::fs::read_to_string(f)
+            .map_err(|e| format!("read auth policy {} failed: {}", f.display(), e))?;
+        let policy: AuthPolicy = serde_json::from_str(js_cont.as_str())
+            .map_err(|e| format!("parse auth policy {} failed: {}", f.display(), e))?;
+        tracing::info!(
+            "auth policy loaded: {} entities, {} fields, {} root fields guarded",
+            policy.entities.len(),
+            policy.fields.len(),
+            policy.root_fields.len()
+        );
+        Ok(policy)
+    }
+
+    ///
+    /// 实体是否允许当前身份访问, 供 seaography 守卫之外的入口(如订阅)复用
+    pub fn entity_allowed(&self, entity: &str, claims: Option<&AuthClaims>) -> bool {
+        allowed(self.entities.get(entity), claims)
+    }
+
+    ///
+    /// 校验操作的自定义根字段; 实体自动生成的根字段由 [`RoleGuard`] 在解析时校验
+    pub fn check_root_fields(
+        &self,
+        doc: &ExecutableDocument,
+        op: &OperationDefinition,
+        claims: Option<&AuthClaims>,
+    ) -> Result<(), DError> {
+        for name in root_fields(doc, op) {
+            let tables = ROOT_FIELD_TABLES
+                .iter()
+                .find(|(field, _)| *field == name)
+                .map(|(_, tables)| *tables)
+                .unwrap_or_default();
+            let denied = !allowed(self.root_fields.get(name), claims)
+                || tables.iter().any(|t| !self.entity_allowed(t, claims));
+            if denied {
+                return Err(DError::Custom(LogicErr::Forbidden(format!(
+                    "permission denied on <{}>",
+                    name
+                ))));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn allowed(roles: Option<&Vec<String>>, claims: Option<&AuthClaims>) -> bool {
+    match roles {
+        None => true,
+        Some(roles) => claims.is_some_and(|c| c.has_any_role(roles)),
+    }
+}
+
+/// seaography 实体/字段守卫, 策略取自 schema data 中的 [`AuthPolicy`],
+/// 角色取自请求级 data 中的 [`AuthClaims`]
+pub struct RoleGuard;
+
+impl RoleGuard {
+    fn check(
+        ctx: &ResolverContext,
+        roles: impl FnOnce(&AuthPolicy) -> Option<&Vec<String>>,
+        name: &str,
+    ) -> GuardAction {
+        // schema 构建时注入, 缺失时拒绝而非放行
+        let Some(policy) = ctx.data_opt::<Arc<AuthPolicy>>() else {
+            return GuardAction::Block(Some("auth policy is not loaded".to_owned()));
+        };
+        if allowed(roles(policy), ctx.data_opt::<AuthClaims>()) {
+            GuardAction::Allow
+        } else {
+            GuardAction::Block(Some(format!("permission denied on <{}>", name)))
+        }
+    }
+
+    ///
+    /// 字段守卫之外新增的派生字段(如 json 列的结构化视图)按原列 `table.column` 校验
+    pub fn check_column(ctx: &ResolverContext, key: &str) -> Result<(), Error> {
+        match Self::check(ctx, |p| p.fields.get(key), key) {
+            GuardAction::Allow => Ok(()),
+            GuardAction::Block(msg) => {
+                Err(Error::new(msg.unwrap_or_else(|| {
+                    format!("permission denied on <{}>", key)
+                })))
+            }
+        }
+    }
+}
+
+impl LifecycleHooksInterface for RoleGuard {
+    fn entity_guard(
+        &self,
+        ctx: &ResolverContext,
+        entity: &str,
+        _action: OperationType,
+    ) -> GuardAction {
+        let entity = camel_to_snake(entity);
+        Self::check(ctx, |p| p.entities.get(&entity), &entity)
+    }
+
+    fn field_guard(
+        &self,
+        ctx: &ResolverContext,
+        entity: &str,
+        field: &str,
+        _action: OperationType,
+    ) -> GuardAction {
+        let key = format!("{}.{}", camel_to_snake(entity), camel_to_snake(field));
+        Self::check(ctx, |p| p.fields.get(&key), &key)
+    }
+}
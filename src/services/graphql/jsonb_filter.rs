@@ -0,0 +1,117 @@
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, ColumnType, Condition, EntityName, EntityTrait, IdenStatic, Iterable};
+use seaography::async_graphql::dynamic::{InputObject, InputValue, ObjectAccessor, TypeRef};
+use seaography::{Builder, BuilderContext, FilterType, FnFilterCondition};
+
+use crate::util::snake_to_camel;
+
+/// json/jsonb 列的过滤输入, 多个操作同时给出时取交集
+pub const JSONB_FILTER_INPUT: &str = "JsonbFilterInput";
+const JSONB_PATH_EQ_INPUT: &str = "JsonbPathEqInput";
+
+///
+/// 为全部实体的 json/jsonb 列替换默认过滤类型:
+/// `contains` (@>), `hasKey` (?, 以 jsonb_exists 表达避免与占位符冲突), `pathEq` (#>> = )
+///
+/// 列表达式优先取 `column_select_expressions`, 与查询输出保持一致(如 labels 数组转 jsonb)
+pub fn register_filters(ctx: &mut BuilderContext) {
+    macro_rules! entity_filters {
+        ([$($module:ident),+ $(,)?]) => {
+            $(entity_filters::<entity_graphql::$module::Entity>(ctx);)+
+        };
+    }
+    for_each_entity!(entity_filters!());
+}
+
+fn entity_filters<E: EntityTrait>(ctx: &mut BuilderContext) {
+    let table = E::default().table_name().to_owned();
+    for column in E::Column::iter() {
+        if !matches!(
+            column.def().get_column_type(),
+            ColumnType::Json | ColumnType::JsonBinary
+        ) {
+            continue;
+        }
+        let name = column.as_str();
+        let expr = ctx
+            .column_select_expressions
+            .get(&format!("{}.{}", table, name))
+            .cloned()
+            .unwrap_or_else(|| format!("\"{}\".\"{}\"", table, name));
+        // seaography 以 `<实体类型名>.<字段名>` 查找覆盖
+        let key = format!(
+            "{}.{}",
+            snake_to_camel(&table, true),
+            snake_to_camel(name, false)
+        );
+        ctx.filter_types.overwrites.insert(
+            key.clone(),
+            Some(FilterType::Custom(JSONB_FILTER_INPUT.to_owned())),
+        );
+        ctx.filter_types
+            .condition_functions
+            .insert(key, jsonb_condition(format!("({})::jsonb", expr)));
+    }
+}
+
+fn jsonb_condition(expr: String) -> FnFilterCondition {
+    Box::new(move |condition: Condition, filter: &ObjectAccessor| {
+        let mut condition = condition;
+        if let Some(value) = filter.get("contains") {
+            let value: serde_json::Value = value.deserialize()?;
+            condition = condition.add(Expr::cust_with_values(
+                format!("{} @> $1::jsonb", expr),
+                [value],
+            ));
+        }
+        if let Some(key) = filter.get("hasKey") {
+            condition = condition.add(Expr::cust_with_values(
+                format!("jsonb_exists({}, $1)", expr),
+                [key.string()?.to_owned()],
+            ));
+        }
+        if let Some(path_eq) = filter.get("pathEq") {
+            let path_eq = path_eq.object()?;
+            let path = path_eq
+                .try_get("path")?
+                .list()?
+                .iter()
+                .map(|p| p.string().map(str::to_owned))
+                .collect::<Result<Vec<String>, _>>()?;
+            let value = path_eq.try_get("value")?.string()?.to_owned();
+            condition = condition.add(Expr::cust_with_values(
+                format!("{} #>> $1::text[] = $2", expr),
+                [sea_orm::Value::from(path), sea_orm::Value::from(value)],
+            ));
+        }
+        Ok(condition)
+    })
+}
+
+///
+/// 注册过滤输入类型, 需与 [`register_filters`] 配合使用
+pub fn register(builder: &mut Builder) {
+    builder.inputs.push(
+        InputObject::new(JSONB_FILTER_INPUT)
+            .description("json/jsonb column filter, operators are combined with AND")
+            .field(
+                InputValue::new("contains", TypeRef::named("Json")).description("column @> value"),
+            )
+            .field(
+                InputValue::new("hasKey", TypeRef::named(TypeRef::STRING))
+                    .description("top-level key (or array string element) exists"),
+            )
+            .field(
+                InputValue::new("pathEq", TypeRef::named(JSONB_PATH_EQ_INPUT))
+                    .description("column #>> path = value, compared as text"),
+            ),
+    );
+    builder.inputs.push(
+        InputObject::new(JSONB_PATH_EQ_INPUT)
+            .field(InputValue::new(
+                "path",
+                TypeRef::named_nn_list_nn(TypeRef::STRING),
+            ))
+            .field(InputValue::new("value", TypeRef::named_nn(TypeRef::STRING))),
+    );
+}
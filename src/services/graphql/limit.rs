@@ -0,0 +1,195 @@
+use seaography::async_graphql::parser::types::{
+    ExecutableDocument, OperationDefinition, Selection, SelectionSet,
+};
+use std::collections::HashMap;
+
+use crate::config::graphql::GraphqlSetting;
+use crate::error::{DError, LogicErr};
+
+///
+/// 解析前的 query 文本大小校验
+pub fn check_query_size(setting: &GraphqlSetting, query: &str) -> Result<(), DError> {
+    match setting.max_query_bytes {
+        Some(max) if query.len() > max => Err(DError::Custom(LogicErr::QueryTooLarge(format!(
+            "query is {} bytes, limit is {}",
+            query.len(),
+            max
+        )))),
+        _ => Ok(()),
+    }
+}
+
+///
+/// 执行前对实际执行的操作校验: 嵌套深度、复杂度、字段别名数量;
+/// 语法错误、未指定 operation name 等无法选出操作的请求交给 schema 执行时报告
+pub fn check_operation(
+    setting: &GraphqlSetting,
+    doc: &ExecutableDocument,
+    op: &OperationDefinition,
+) -> Result<(), DError> {
+    if setting.depth_limit.is_none()
+        && setting.complexity_limit.is_none()
+        && setting.max_aliases.is_none()
+    {
+        return Ok(());
+    }
+    let cost = Measure::new(doc).selection_set(&op.selection_set.node);
+    if let Some(max) = setting.depth_limit.filter(|max| cost.depth > *max) {
+        return Err(DError::Custom(LogicErr::QueryTooDeep(format!(
+            "query depth is {}, limit is {}",
+            cost.depth, max
+        ))));
+    }
+    if let Some(max) = setting.complexity_limit.filter(|max| cost.fields > *max) {
+        return Err(DError::Custom(LogicErr::QueryTooComplex(format!(
+            "query complexity is {}, limit is {}",
+            cost.fields, max
+        ))));
+    }
+    if let Some(max) = setting.max_aliases.filter(|max| cost.aliases > *max) {
+        return Err(DError::Custom(LogicErr::TooManyAliases(format!(
+            "query uses {} aliases, limit is {}",
+            cost.aliases, max
+        ))));
+    }
+    Ok(())
+}
+
+/// 选择集展开 fragment 后的规模
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Cost {
+    // 最大字段嵌套层数, 根字段为 1
+    depth: usize,
+    // 字段总数, 即复杂度(每个字段计 1)
+    fields: usize,
+    // 带别名的字段数
+    aliases: usize,
+}
+
+impl Cost {
+    fn add(&mut self, other: Cost) {
+        self.depth = self.depth.max(other.depth);
+        self.fields = self.fields.saturating_add(other.fields);
+        self.aliases = self.aliases.saturating_add(other.aliases);
+    }
+}
+
+/// 按 fragment 展开次数计算规模; 每个 fragment 只计算一次,
+/// 避免多层重复引用时按展开后的大小遍历
+struct Measure<'a> {
+    doc: &'a ExecutableDocument,
+    // None 表示正在计算(循环引用, 由 schema 校验阶段报错, 此处计 0)
+    fragments: HashMap<&'a str, Option<Cost>>,
+}
+
+impl<'a> Measure<'a> {
+    fn new(doc: &'a ExecutableDocument) -> Self {
+        Measure {
+            doc,
+            fragments: HashMap::new(),
+        }
+    }
+
+    fn selection_set(&mut self, set: &'a SelectionSet) -> Cost {
+        let mut cost = Cost::default();
+        for item in set.items.iter() {
+            match &item.node {
+                Selection::Field(f) => {
+                    let child = self.selection_set(&f.node.selection_set.node);
+                    cost.add(Cost {
+                        depth: child.depth + 1,
+                        fields: child.fields.saturating_add(1),
+                        aliases: child
+                            .aliases
+                            .saturating_add(f.node.alias.is_some() as usize),
+                    });
+                }
+                Selection::InlineFragment(f) => {
+                    let child = self.selection_set(&f.node.selection_set.node);
+                    cost.add(child);
+                }
+                Selection::FragmentSpread(s) => {
+                    let child = self.fragment(s.node.fragment_name.node.as_str());
+                    cost.add(child);
+                }
+            }
+        }
+        cost
+    }
+
+    fn fragment(&mut self, name: &'a str) -> Cost {
+        if let Some(cached) = self.fragments.get(name) {
+            return cached.unwrap_or_default();
+        }
+        let Some(frag) = self.doc.fragments.get(name) else {
+            return Cost::default();
+        };
+        self.fragments.insert(name, None);
+        let cost = self.selection_set(&frag.node.selection_set.node);
+        self.fragments.insert(name, Some(cost));
+        cost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::graphql::operation::selected_operation;
+    use seaography::async_graphql::parser::parse_query;
+
+    fn cost(query: &str) -> Cost {
+        let doc = parse_query(query).unwrap();
+        let op = selected_operation(&doc, None).unwrap();
+        Measure::new(&doc).selection_set(&op.selection_set.node)
+    }
+
+    #[test]
+    fn counts_nested_fields() {
+        let c = cost("{ solution { nodes { id name } } }");
+        assert_eq!(
+            c,
+            Cost {
+                depth: 3,
+                fields: 4,
+                aliases: 0
+            }
+        );
+    }
+
+    #[test]
+    fn counts_aliases_per_spread() {
+        let c = cost(
+            "{ a: solution { ...F } b: solution { ...F } }
+             fragment F on SolutionConnection { x: nodes { id } y: nodes { id } }",
+        );
+        assert_eq!(c.aliases, 2 + 2 * 2);
+        assert_eq!(c.fields, 2 + 2 * 4);
+        assert_eq!(c.depth, 3);
+    }
+
+    #[test]
+    fn unused_fragment_is_not_counted() {
+        let c = cost("{ a: id } fragment F on Query { x: id y: id }");
+        assert_eq!(c.aliases, 1);
+    }
+
+    #[test]
+    fn repeated_fragments_do_not_blow_up() {
+        // 每层引用下一层两次, 展开后规模为 2^n
+        let mut query = "{ ...F0 }".to_owned();
+        for i in 0..40 {
+            query.push_str(&format!(
+                " fragment F{i} on Query {{ ...F{n} ...F{n} }}",
+                n = i + 1
+            ));
+        }
+        query.push_str(" fragment F40 on Query { id }");
+        assert_eq!(cost(&query).fields, 1 << 40);
+    }
+
+    #[test]
+    fn cyclic_fragment_terminates() {
+        let c = cost("{ ...A } fragment A on Query { id ...B } fragment B on Query { ...A }");
+        assert_eq!(c.fields, 1);
+    }
+}
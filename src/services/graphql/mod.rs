@@ -1,44 +1,407 @@
-mod custom_query;
-mod query_root;
+/// entity_graphql 中的全部实体模块, 以 `[module, ...]` 追加到回调宏参数末尾展开
+macro_rules! for_each_entity {
+    ($callback:ident!($($args:tt)*)) => {
+        $callback!($($args)* [
+            artifactory,
+            artifactory_runtime,
+            doc_module_versions,
+            doc_modules,
+            doc_versions,
+            fc_cfg_approval_flow,
+            feature_config,
+            feature_config_conflict,
+            feature_config_history,
+            feature_config_label_inc,
+            feature_config_label_lv1,
+            feature_config_label_lv2,
+            feature_config_label_lv3,
+            feature_config_label_lv4,
+            feature_config_label_strategy_labels,
+            feature_config_layer_rule_ids,
+            feature_config_layer_rule_zh_cn,
+            feature_config_layers,
+            feature_config_layers_sol_all,
+            feature_setting,
+            feature_tag_config,
+            feature_tag_config_history,
+            mod_app,
+            solution,
+            solution_draft,
+            solution_history,
+            solution_label,
+            solution_way,
+            solution_way_history,
+            solution_workflow,
+            st_polling_log,
+            st_polling_task,
+            st_wf_approval_flow,
+            st_wf_sol_pack,
+            st_wf_sol_pack_deploy_log,
+            st_wf_solution,
+            st_wf_solution_item,
+            st_workflow,
+            st_yunxiao_blackbox_test_events,
+            st_yunxiao_blackbox_test_events_history,
+            st_yunxiao_task_events,
+            st_yunxiao_task_events_history,
+        ])
+    };
+}
+
+/// 全部实体的表名
+pub const ENTITY_TABLES: &[&str] = {
+    macro_rules! entity_tables {
+        ([$($module:ident),+ $(,)?]) => {
+            &[$(stringify!($module)),+]
+        };
+    }
+    for_each_entity!(entity_tables!())
+};
+
+mod cache;
+pub(crate) mod custom_query;
+pub mod guard;
+mod jsonb_filter;
+mod limit;
+pub mod mutation;
+mod operation;
+pub mod query_root;
+pub mod subscription;
+mod typed_json;
 use actix_web::web;
+use actix_web::HttpMessage;
+use actix_web::HttpRequest;
 use actix_web::HttpResponse;
 use actix_web::Result;
 use async_graphql_actix_web::GraphQLRequest;
 use async_graphql_actix_web::GraphQLResponse;
+use async_graphql_actix_web::GraphQLSubscription;
+use sea_orm::{DatabaseConnection, DbErr};
 use seaography::async_graphql::dynamic::*;
 use seaography::async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
-use seaography::BuilderContext;
+use seaography::async_graphql::parser::parse_query;
+use seaography::async_graphql::parser::types::OperationType;
+use seaography::async_graphql::{Data, Request, Response, ServerError, Value};
+use seaography::{BuilderContext, LifecycleHooks};
 use serde_json::json;
+use std::sync::{Arc, PoisonError, RwLock};
 
-use crate::error::DResult;
+use crate::config::graphql::GraphqlSetting;
+use crate::config::reload::SharedSetting;
+use crate::dao::seaorm_mysql::AppState;
+use crate::error::{DError, DResult, LogicErr, DB_ERR_CODE, UNKNOWN_ERR_CODE};
+use crate::feature_tag;
+use crate::feature_tag::eval::PlayerContext;
+use crate::middleware::auth::AuthClaims;
 use crate::services::vo::RespVO;
+use cache::CachePlan;
+use guard::{AuthPolicy, RoleGuard};
+use subscription::SubscriptionSchema;
+use typed_json::TypedColumn;
+
+/// `labels` 列实际为 postgres 数组, 查询时转换为 jsonb 与实体定义对齐
+pub const LABELS_SELECT_EXPR: &str = "array_to_json(\"labels\")::jsonb";
 
 lazy_static::lazy_static! {
     pub static ref GRAPHQL_BUILD_CTX: BuilderContext = {
         let mut ctx = BuilderContext::default();
         ctx.column_select_expressions.insert(
             "feature_config.labels".into(),
-            "array_to_json(\"labels\")::jsonb".into(),
+            LABELS_SELECT_EXPR.into(),
         );
         ctx.column_select_expressions.insert(
             "feature_config_history.labels".into(),
-            "array_to_json(\"labels\")::jsonb".into(),
+            LABELS_SELECT_EXPR.into(),
         );
+        // json 列的 containment/key/path 过滤, 需在列表达式注册之后
+        jsonb_filter::register_filters(&mut ctx);
+        // 按角色隐藏实体/字段, 策略在 schema 构建时以 data 注入
+        ctx.hooks = LifecycleHooks::new(RoleGuard);
         ctx
     };
+    /// 以结构体定义 graphql 类型的 json 列, 结构化视图为新增的 `<field>Typed`, 原 Json 字段不变;
+    /// solution.tag / list_layer / pack_data / deploy_info 等列格式未经样例数据确认, 暂不注册
+    pub static ref TYPED_JSON_COLUMNS: Vec<TypedColumn> = {
+        use entity_graphql::*;
+        use self::typed_json::*;
+        vec![
+            TypedColumn::new::<feature_config::Entity, StrategyIds>(
+                feature_config::Column::StrategyActual,
+            ),
+            TypedColumn::new::<feature_config::Entity, StrategyIds>(
+                feature_config::Column::StrategyExpect,
+            ),
+            TypedColumn::new::<feature_config::Entity, StrategyIds>(
+                feature_config::Column::StrategyDemotion,
+            ),
+        ]
+    };
+}
+
+/// 对外服务的 schema; mutation 白名单在构建时写入, 热更新时重建
+#[derive(Clone)]
+pub struct GqlSchema(Arc<RwLock<Schema>>);
+
+impl GqlSchema {
+    ///
+    /// 按当前配置构建 schema, 并在影响构建的配置变更后重建; 重建失败时保留旧 schema
+    pub fn spawn(
+        database: DatabaseConnection,
+        shared: &SharedSetting,
+        policy: Arc<AuthPolicy>,
+    ) -> Result<Self, SchemaError> {
+        let mut rx = shared.subscribe();
+        let mut applied = rx.borrow_and_update().graphql.clone();
+        let schema = query_root::schema(database.clone(), &applied, policy.clone())?;
+        let holder = GqlSchema(Arc::new(RwLock::new(schema)));
+        let target = holder.clone();
+        tokio::spawn(async move {
+            while rx.changed().await.is_ok() {
+                let setting = rx.borrow_and_update().graphql.clone();
+                if !Self::affects_build(&applied, &setting) {
+                    continue;
+                }
+                match query_root::schema(database.clone(), &setting, policy.clone()) {
+                    Ok(schema) => {
+                        *target.0.write().unwrap_or_else(PoisonError::into_inner) = schema;
+                        tracing::info!("[config] graphql schema rebuilt");
+                    }
+                    Err(e) => tracing::error!("[config] rebuild graphql schema failed: {:?}", e),
+                }
+                applied = setting;
+            }
+        });
+        Ok(holder)
+    }
+
+    ///
+    /// 当前 schema, 单个请求内应只取一次
+    pub fn load(&self) -> Schema {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn affects_build(old: &GraphqlSetting, new: &GraphqlSetting) -> bool {
+        old.mutation_entities != new.mutation_entities
+    }
+}
+
+///
+/// 查询请求路由到只读副本(请求级 data 覆盖 schema 的主库连接)，mutation 及无法选出操作的请求走主库
+fn route_request(state: &AppState, req: Request, ty: Option<OperationType>) -> Request {
+    if ty == Some(OperationType::Query) {
+        let conn = state.read_conn().clone();
+        return req.data(conn);
+    }
+    req
+}
+
+///
+/// 执行 graphql 请求: 请求级限制 + 身份注入 + 读副本路由 + redis 查询缓存
+async fn execute(
+    schema: &Schema,
+    state: &AppState,
+    policy: &AuthPolicy,
+    http_req: &HttpRequest,
+    req: Request,
+) -> Result<Response, DError> {
+    let setting = state.rtx_setting.load();
+    limit::check_query_size(&setting.graphql, &req.query)?;
+    let claims = http_req.extensions().get::<AuthClaims>().cloned();
+    // 只解析一次, 限制校验、策略校验、路由与缓存共用; 语法错误交给 schema 报告
+    let doc = parse_query(&req.query).ok();
+    let op = doc
+        .as_ref()
+        .and_then(|doc| operation::selected_operation(doc, req.operation_name.as_deref()));
+    if let (Some(doc), Some(op)) = (&doc, op) {
+        limit::check_operation(&setting.graphql, doc, op)?;
+        // 自定义根字段不经过 seaography 实体守卫, 执行前按策略校验
+        policy.check_root_fields(doc, op, claims.as_ref())?;
+    }
+    let ty = op.map(|op| op.ty);
+    // 不同角色可见字段不同, 缓存按角色集合隔离
+    let scope = claims
+        .as_ref()
+        .map(|c| {
+            let mut roles = c.roles.clone();
+            roles.sort();
+            roles.join(",")
+        })
+        .unwrap_or_default();
+    let (plan, mutated) = match (&doc, op) {
+        (Some(doc), Some(op)) if op.ty == OperationType::Mutation => {
+            (None, cache::mutated_tables(doc, op))
+        }
+        (Some(doc), Some(op)) => (
+            CachePlan::from_request(&setting.cache, &req, doc, op, &scope),
+            vec![],
+        ),
+        _ => (None, vec![]),
+    };
+    let mut redis = state.redis_pool.clone();
+    if let (Some(plan), Some(conn)) = (&plan, redis.as_mut()) {
+        if let Some(resp) = plan.get(conn).await {
+            return Ok(resp);
+        }
+    }
+    // 覆盖 schema 构建时的 GraphqlSetting, 使热更新的检索配置生效
+    let req = req
+        .data(setting.graphql.clone())
+        .data(setting.polling.status_codes);
+    let req = match claims {
+        Some(claims) => req.data(claims),
+        None => req,
+    };
+    let mut resp = schema.execute(route_request(state, req, ty)).await;
+    tag_error_codes(&mut resp);
+    if let (Some(plan), Some(conn)) = (&plan, redis.as_mut()) {
+        if let Err(e) = plan.put(conn, &resp, setting.cache.max_ttl()).await {
+            tracing::warn!("[gql cache] put <{}> failed: {:?}", plan.key, e);
+        }
+    }
+    // 变更后清空涉及表的缓存, 失败时缓存最迟在 TTL 后过期
+    if let Some(conn) = redis.as_mut() {
+        for table in mutated.iter() {
+            if let Err(e) = cache::invalidate_entity(conn, table).await {
+                tracing::warn!("[gql cache] invalidate <{}> failed: {:?}", table, e);
+            }
+        }
+    }
+    Ok(resp)
+}
+
+///
+/// 为 graphql 错误补充 `extensions.code`: 解析函数返回的 [`DError`] 取其错误码,
+/// 解析/校验阶段(无 path)的错误视为参数错误, 其余为未知错误
+fn tag_error_codes(resp: &mut Response) {
+    for e in resp.errors.iter_mut() {
+        if error_code(e).is_some() {
+            continue;
+        }
+        let code = match (e.source::<DError>(), e.source::<DbErr>()) {
+            (Some(err), _) => err.err_code(),
+            (None, Some(_)) => DB_ERR_CODE,
+            _ if e.path.is_empty() => LogicErr::ParamsError(String::new()).code(),
+            _ => UNKNOWN_ERR_CODE,
+        };
+        e.extensions
+            .get_or_insert_with(Default::default)
+            .set("code", code);
+    }
+}
+
+fn error_code(e: &ServerError) -> Option<i32> {
+    match e.extensions.as_ref()?.get("code")? {
+        Value::Number(n) => n.as_i64().map(|n| n as i32),
+        _ => None,
+    }
+}
+
+///
+/// `{code, msg, data, errors}` 形式的 graphql 响应, 有错误时 code/msg 取第一个错误,
+/// errors 保留 graphql 规范中的 message/locations/path/extensions
+pub async fn graphql_json(
+    schema: web::Data<GqlSchema>,
+    state: web::Data<AppState>,
+    policy: web::Data<AuthPolicy>,
+    http_req: HttpRequest,
+    req: GraphQLRequest,
+) -> DResult {
+    let resp = execute(&schema.load(), &state, &policy, &http_req, req.into_inner()).await?;
+    let mut vo = RespVO::from(&resp.data);
+    if let Some(first) = resp.errors.first() {
+        let errors = resp
+            .errors
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<_>, _>>()?;
+        vo = vo.with_errors(
+            error_code(first).unwrap_or(UNKNOWN_ERR_CODE),
+            &first.message,
+            errors,
+        );
+    }
+    Ok(HttpResponse::Ok().json(vo))
+}
+
+pub async fn graphql_index(
+    schema: web::Data<GqlSchema>,
+    state: web::Data<AppState>,
+    policy: web::Data<AuthPolicy>,
+    http_req: HttpRequest,
+    req: GraphQLRequest,
+) -> Result<GraphQLResponse, DError> {
+    let resp = execute(&schema.load(), &state, &policy, &http_req, req.into_inner()).await?;
+    Ok(resp.into())
+}
+
+///
+/// 按实体(表名)清空查询缓存, 仅限管理角色
+pub async fn graphql_cache_invalidate(
+    state: web::Data<AppState>,
+    http_req: HttpRequest,
+    entity: web::Path<String>,
+) -> DResult {
+    let admin_roles = state.rtx_setting.load().auth.admin_roles.clone();
+    let is_admin = http_req
+        .extensions()
+        .get::<AuthClaims>()
+        .is_some_and(|c| c.has_any_role(&admin_roles));
+    if !is_admin {
+        return Err(DError::Custom(LogicErr::Forbidden(
+            "cache invalidation requires an admin role".to_owned(),
+        )));
+    }
+    let Some(mut conn) = state.redis_pool.clone() else {
+        return Err(DError::Custom(LogicErr::NotFound("redis pool".to_owned())));
+    };
+    let cnt = cache::invalidate_entity(&mut conn, &entity).await?;
+    tracing::info!("[gql cache] invalidate <{}>, {} keys removed", entity, cnt);
+    Ok(HttpResponse::Ok().json(RespVO::from(&cnt)))
 }
 
-pub async fn graphql_json(schema: web::Data<Schema>, req: GraphQLRequest) -> DResult {
-    let resp = schema.execute(req.into_inner()).await;
-    Ok(HttpResponse::Ok().json(RespVO::from(&resp.data)))
+///
+/// 对玩家上下文判定 feature 标签, 与 graphql 查询 `evaluateFeatureTags` 结果一致
+pub async fn feature_tag_evaluate(
+    state: web::Data<AppState>,
+    policy: web::Data<AuthPolicy>,
+    http_req: HttpRequest,
+    body: web::Json<PlayerContext>,
+) -> DResult {
+    let claims = http_req.extensions().get::<AuthClaims>().cloned();
+    for table in ["feature_config", "feature_tag_config"] {
+        if !policy.entity_allowed(table, claims.as_ref()) {
+            return Err(DError::Custom(LogicErr::Forbidden(format!(
+                "permission denied on <{}>",
+                table
+            ))));
+        }
+    }
+    let evaluation = feature_tag::service::evaluate(state.read_conn(), body.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(RespVO::from(&serde_json::to_value(&evaluation)?)))
 }
 
-pub async fn graphql_index(schema: web::Data<Schema>, req: GraphQLRequest) -> GraphQLResponse {
-    schema.execute(req.into_inner()).await.into()
+///
+/// graphql-ws 订阅入口, 握手请求已通过鉴权中间件, 身份透传给订阅解析
+pub async fn graphql_ws(
+    schema: web::Data<SubscriptionSchema>,
+    http_req: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse> {
+    let mut data = Data::default();
+    if let Some(claims) = http_req.extensions().get::<AuthClaims>().cloned() {
+        data.insert(claims);
+    }
+    GraphQLSubscription::new(schema.0.clone())
+        .with_data(data)
+        .start(&http_req, payload)
 }
 
 pub async fn graphql_playground() -> Result<HttpResponse> {
-    let p_source = playground_source(GraphQLPlaygroundConfig::new("/gql/"));
+    let p_source =
+        playground_source(GraphQLPlaygroundConfig::new("/gql/").subscription_endpoint("/gql/ws"));
     Ok(HttpResponse::Ok()
         .content_type("text/html; charset=utf-8")
         .body(p_source))
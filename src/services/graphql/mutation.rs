@@ -0,0 +1,25 @@
+use entity_graphql::*;
+use seaography::Builder;
+
+/// 按白名单为实体注册 create/update/delete mutation
+macro_rules! register_allowed_mutations {
+    ($builder:ident, $allow:ident, [$($module:ident),+ $(,)?]) => {{
+        let known: &[&str] = &[$(stringify!($module)),+];
+        for name in $allow.iter().filter(|n| !known.contains(&n.as_str())) {
+            tracing::warn!("unknown entity <{}> in mutation allowlist, ignored", name);
+        }
+        $(
+            if $allow.iter().any(|n| n == stringify!($module)) {
+                $builder.register_entity_mutations::<$module::Entity, $module::ActiveModel>();
+                tracing::info!("graphql mutations enabled for <{}>", stringify!($module));
+            }
+        )+
+    }};
+}
+
+///
+/// 只为白名单内的实体开启 mutation，其余实体保持只读
+pub fn register_entity_mutations(mut builder: Builder, allow: &[String]) -> Builder {
+    for_each_entity!(register_allowed_mutations!(builder, allow,));
+    builder
+}
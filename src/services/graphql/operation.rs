@@ -0,0 +1,82 @@
+use seaography::async_graphql::parser::types::{
+    DocumentOperations, ExecutableDocument, OperationDefinition, Selection, SelectionSet,
+};
+use std::collections::HashSet;
+
+///
+/// 请求实际执行的操作, 多操作文档按 operation name 选取
+pub fn selected_operation<'a>(
+    doc: &'a ExecutableDocument,
+    name: Option<&str>,
+) -> Option<&'a OperationDefinition> {
+    match &doc.operations {
+        DocumentOperations::Single(op) => Some(&op.node),
+        DocumentOperations::Multiple(ops) => name.and_then(|n| ops.get(n)).map(|op| &op.node),
+    }
+}
+
+///
+/// 操作的根字段名(不含别名), 根级 fragment 展开
+pub fn root_fields<'a>(doc: &'a ExecutableDocument, op: &'a OperationDefinition) -> Vec<&'a str> {
+    let mut out = vec![];
+    walk_fields(doc, &op.selection_set.node, &mut |name, depth| {
+        if depth == 0 {
+            out.push(name);
+        }
+    });
+    out
+}
+
+///
+/// 遍历选择集中出现的字段(不含别名), `depth` 为根字段起的嵌套层数; 用于收集字段名,
+/// 同一 fragment 在同一层只展开一次, 循环引用的 fragment 不再展开(由 schema 校验阶段报错)
+pub fn walk_fields<'a>(
+    doc: &'a ExecutableDocument,
+    set: &'a SelectionSet,
+    visit: &mut impl FnMut(&'a str, usize),
+) {
+    let mut walker = Walker {
+        doc,
+        spreading: vec![],
+        expanded: HashSet::new(),
+    };
+    walker.walk(set, 0, visit);
+}
+
+struct Walker<'a> {
+    doc: &'a ExecutableDocument,
+    // 正在展开的 fragment 链
+    spreading: Vec<&'a str>,
+    // 已展开过的 (fragment, 层数)
+    expanded: HashSet<(&'a str, usize)>,
+}
+
+impl<'a> Walker<'a> {
+    fn walk(
+        &mut self,
+        set: &'a SelectionSet,
+        depth: usize,
+        visit: &mut impl FnMut(&'a str, usize),
+    ) {
+        for item in set.items.iter() {
+            match &item.node {
+                Selection::Field(f) => {
+                    visit(f.node.name.node.as_str(), depth);
+                    self.walk(&f.node.selection_set.node, depth + 1, visit);
+                }
+                Selection::InlineFragment(f) => self.walk(&f.node.selection_set.node, depth, visit),
+                Selection::FragmentSpread(s) => {
+                    let name = s.node.fragment_name.node.as_str();
+                    if self.spreading.contains(&name) || !self.expanded.insert((name, depth)) {
+                        continue;
+                    }
+                    if let Some(frag) = self.doc.fragments.get(&s.node.fragment_name.node) {
+                        self.spreading.push(name);
+                        self.walk(&frag.node.selection_set.node, depth, visit);
+                        self.spreading.pop();
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,19 +1,44 @@
 use async_graphql::dynamic::*;
 use sea_orm::DatabaseConnection;
-use seaography::{async_graphql, lazy_static, Builder, BuilderContext};
+use seaography::{async_graphql, Builder};
 
-lazy_static::lazy_static! { static ref CONTEXT : BuilderContext = BuilderContext :: default () ; }
+use std::sync::Arc;
 
+use super::{
+    custom_query, guard::AuthPolicy, jsonb_filter, mutation, typed_json, GRAPHQL_BUILD_CTX,
+    TYPED_JSON_COLUMNS,
+};
+use crate::config::graphql::GraphqlSetting;
+
+///
+/// 构建对外提供服务的 schema: 实体只读查询 + 白名单 mutation + 深度/复杂度限制
 pub fn schema(
     database: DatabaseConnection,
-    depth: Option<usize>,
-    complexity: Option<usize>,
+    setting: &GraphqlSetting,
+    policy: Arc<AuthPolicy>,
 ) -> Result<Schema, SchemaError> {
-    let builder = Builder::new(&CONTEXT, database.clone());
-    builder
-        .set_depth_limit(depth)
-        .set_complexity_limit(complexity)
+    let mut builder = Builder::new(&GRAPHQL_BUILD_CTX, database.clone());
+
+    // 注册 active enums（如果数据库有枚举类型）
+    // 如果数据库没有枚举，sea-orm-cli 不会生成 register_active_enums 函数
+    // 此时需要手动注释掉下面这行，或者在 entity_graphql/src/lib.rs 中添加空实现
+    // builder = entity_graphql::register_active_enums(builder);
+
+    builder = entity_graphql::register_entity_modules(builder);
+    // 只读实体之外，按白名单开启 mutation
+    builder = mutation::register_entity_mutations(builder, &setting.mutation_entities);
+    builder = custom_query::register_custom_queries(builder);
+    jsonb_filter::register(&mut builder);
+    // json 列的结构化类型, 需在实体输出对象注册之后
+    typed_json::register_typed_columns(&mut builder, &TYPED_JSON_COLUMNS);
+    // 深度/复杂度在执行前由 limit::check_operation 校验, 以返回结构化错误码
+    let schema = builder
         .schema_builder()
         .data(database)
-        .finish()
+        .data(setting.clone())
+        .data(policy);
+    // 操作耗时/解析错误指标
+    #[cfg(feature = "metrics")]
+    let schema = schema.extension(crate::metrics::graphql::GqlMetrics::new());
+    schema.finish()
 }
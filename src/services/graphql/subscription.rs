@@ -0,0 +1,94 @@
+use seaography::async_graphql::dynamic::{
+    Field, FieldFuture, FieldValue, InputValue, Object, Schema, SchemaError, Subscription,
+    SubscriptionField, SubscriptionFieldFuture, TypeRef,
+};
+use seaography::async_graphql::{Error, Value};
+use std::sync::Arc;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::StreamExt;
+
+use super::custom_query::{id_arg, json_object, JsonField};
+use super::guard::AuthPolicy;
+use crate::dao::notify::StateBus;
+use crate::error::{DError, LogicErr};
+use crate::middleware::auth::AuthClaims;
+
+/// 订阅字段 -> 表名
+const STATE_SUBSCRIPTIONS: &[(&str, &str)] = &[
+    ("workflowStateChanged", "st_workflow"),
+    ("solPackStateChanged", "st_wf_sol_pack"),
+    ("pollingTaskStatusChanged", "st_polling_task"),
+];
+
+/// 订阅 schema, 与 seaography 查询 schema 分开注册(两者类型相同, 需要区分 app_data)
+#[derive(Clone)]
+pub struct SubscriptionSchema(pub Schema);
+
+///
+/// 构建订阅 schema: 每个订阅按表名 + 主键过滤 [`StateBus`] 中的状态变更
+pub fn schema(bus: StateBus, policy: Arc<AuthPolicy>) -> Result<SubscriptionSchema, SchemaError> {
+    // graphql 规范要求存在 Query 根类型
+    let query = Object::new("Query").field(Field::new(
+        "health",
+        TypeRef::named_nn(TypeRef::STRING),
+        |_| FieldFuture::new(async { Ok(Some(Value::from("ok"))) }),
+    ));
+    let subscription = STATE_SUBSCRIPTIONS
+        .iter()
+        .fold(Subscription::new("Subscription"), |s, (name, table)| {
+            s.field(state_subscription(name, table))
+        });
+    let schema = Schema::build("Query", None, Some("Subscription"))
+        .register(query)
+        .register(subscription)
+        .register(json_object(
+            "StateChange",
+            vec![
+                JsonField::Scalar("table", TypeRef::named_nn(TypeRef::STRING)),
+                JsonField::Scalar("id", TypeRef::named_nn(TypeRef::ID)),
+                JsonField::Scalar("state", TypeRef::named(TypeRef::STRING)),
+                JsonField::Scalar("prevState", TypeRef::named(TypeRef::STRING)),
+                JsonField::Scalar("at", TypeRef::named(TypeRef::STRING)),
+            ],
+        ))
+        .data(bus)
+        .data(policy)
+        .finish()?;
+    Ok(SubscriptionSchema(schema))
+}
+
+///
+/// 订阅单条记录的状态变更, 实体访问权限与查询接口共用 [`AuthPolicy`]
+fn state_subscription(name: &'static str, table: &'static str) -> SubscriptionField {
+    SubscriptionField::new(name, TypeRef::named_nn("StateChange"), move |ctx| {
+        SubscriptionFieldFuture::new(async move {
+            let id = id_arg(&ctx, "id")?;
+            let policy = ctx.data::<Arc<AuthPolicy>>()?;
+            if !policy.entity_allowed(table, ctx.data_opt::<AuthClaims>()) {
+                return Err(DError::Custom(LogicErr::Forbidden(format!(
+                    "permission denied on <{}>",
+                    table
+                )))
+                .into());
+            }
+            let rx = ctx.data::<StateBus>()?.subscribe();
+            Ok(BroadcastStream::new(rx).filter_map(move |msg| match msg {
+                Ok(change) if change.table == table && change.id == id => Some(
+                    serde_json::to_value(&change)
+                        .map_err(Error::from)
+                        .map(|mut v| {
+                            v["id"] = change.id.to_string().into();
+                            FieldValue::owned_any(v)
+                        }),
+                ),
+                Ok(_) => None,
+                Err(BroadcastStreamRecvError::Lagged(n)) => {
+                    tracing::warn!("[subscription] <{}> lagged, {} changes dropped", name, n);
+                    None
+                }
+            }))
+        })
+    })
+    .argument(InputValue::new("id", TypeRef::named_nn(TypeRef::ID)))
+}
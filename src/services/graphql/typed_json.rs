@@ -0,0 +1,167 @@
+use sea_orm::{EntityName, EntityTrait, IdenStatic, ModelTrait};
+use seaography::async_graphql::dynamic::{Field, FieldFuture, Object, TypeRef};
+use seaography::Builder;
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashSet;
+
+use super::custom_query::json_output;
+use super::guard::RoleGuard;
+use crate::util::snake_to_camel;
+
+/// 以 Rust 结构体描述的 json 列类型
+///
+/// 结构体负责 serde 校验, graphql 输出对象通过 `custom_query::json_object` 声明, 字段与 serde 序列化结果一致
+pub trait TypedJson: DeserializeOwned + Serialize + Send + Sync + 'static {
+    /// 列在 graphql 中的类型
+    fn type_ref() -> TypeRef;
+    /// 类型依赖的输出对象
+    fn objects() -> Vec<Object>;
+}
+
+impl<T: TypedJson> TypedJson for Vec<T> {
+    fn type_ref() -> TypeRef {
+        TypeRef::List(Box::new(TypeRef::NonNull(Box::new(T::type_ref()))))
+    }
+
+    fn objects() -> Vec<Object> {
+        T::objects()
+    }
+}
+
+/// 一个 `entity.column` 的类型注册
+///
+/// 注册后实体输出对象新增 `<field>Typed` 字段, 原 Json 字段及其字段守卫不变;
+/// 值与结构体不符时 `<field>Typed` 返回 null, 以原 Json 字段为准
+pub struct TypedColumn {
+    // 实体 graphql 类型名, 如 StWorkflow
+    object: String,
+    field: String,
+    objects: fn() -> Vec<Object>,
+    typed_field: Box<dyn Fn(&str) -> Field + Send + Sync>,
+}
+
+impl TypedColumn {
+    pub fn new<E, T>(column: E::Column) -> Self
+    where
+        E: EntityTrait,
+        E::Model: Sync + 'static,
+        E::Column: Send + Sync,
+        T: TypedJson,
+    {
+        let table = E::default().table_name().to_owned();
+        let key = format!("{}.{}", table, column.as_str());
+        TypedColumn {
+            object: snake_to_camel(&table, true),
+            field: snake_to_camel(column.as_str(), false),
+            objects: T::objects,
+            typed_field: Box::new(move |name: &str| {
+                let key = key.clone();
+                Field::new(name, T::type_ref(), move |ctx| {
+                    let key = key.clone();
+                    FieldFuture::new(async move {
+                        // 派生字段不经过 seaography 字段守卫, 按原列策略校验
+                        RoleGuard::check_column(&ctx, &key)?;
+                        let Some(raw) =
+                            column_json::<E>(ctx.parent_value.try_downcast_ref()?, column)
+                        else {
+                            return Ok(None);
+                        };
+                        match serde_json::from_value::<T>(raw) {
+                            Ok(typed) => json_output(&typed),
+                            Err(e) => {
+                                tracing::debug!("{} does not match typed view: {}", key, e);
+                                Ok(None)
+                            }
+                        }
+                    })
+                })
+            }),
+        }
+    }
+}
+
+fn column_json<E: EntityTrait>(model: &E::Model, column: E::Column) -> Option<serde_json::Value> {
+    match model.get(column) {
+        sea_orm::Value::Json(Some(v)) if !v.is_null() => Some(*v),
+        _ => None,
+    }
+}
+
+///
+/// 将类型注册应用到实体输出对象, 在实体与自定义查询注册完成后调用
+pub fn register_typed_columns(builder: &mut Builder, columns: &[TypedColumn]) {
+    let mut names: HashSet<String> = builder
+        .outputs
+        .iter()
+        .map(|o| o.type_name().to_owned())
+        .collect();
+    for col in columns {
+        for obj in (col.objects)() {
+            if names.insert(obj.type_name().to_owned()) {
+                builder.outputs.push(obj);
+            }
+        }
+    }
+    builder.outputs = std::mem::take(&mut builder.outputs)
+        .into_iter()
+        .map(|obj| {
+            columns
+                .iter()
+                .filter(|col| col.object == obj.type_name())
+                .fold(obj, |obj, col| {
+                    obj.field((col.typed_field)(&format!("{}Typed", col.field)))
+                })
+        })
+        .collect();
+}
+
+// 只注册已确认格式的列; 其它 json 列在有样例数据确认结构前保持原 Json 字段
+
+/// feature_config.strategy_*, 与 feature_config_layer_rule_ids.strategy_*_ids 相同的 id 列表,
+/// 元素可为整数或数字字符串; 其它形式视为不匹配
+#[derive(Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct StrategyIds(pub Vec<i32>);
+
+impl<'de> Deserialize<'de> for StrategyIds {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        let v = serde_json::Value::deserialize(de)?;
+        let arr = v
+            .as_array()
+            .ok_or_else(|| D::Error::custom("expected an id array"))?;
+        arr.iter()
+            .map(|e| match e {
+                serde_json::Value::Number(n) => n.as_i64().and_then(|n| i32::try_from(n).ok()),
+                serde_json::Value::String(s) => s.trim().parse().ok(),
+                _ => None,
+            })
+            .collect::<Option<Vec<i32>>>()
+            .map(StrategyIds)
+            .ok_or_else(|| D::Error::custom(format!("invalid id in {}", v)))
+    }
+}
+
+impl TypedJson for StrategyIds {
+    fn type_ref() -> TypeRef {
+        TypeRef::named_nn_list(TypeRef::INT)
+    }
+
+    fn objects() -> Vec<Object> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn strategy_ids_accept_numbers_and_numeric_strings() {
+        let ids: StrategyIds = serde_json::from_value(json!([1, "2", " 3 "])).unwrap();
+        assert_eq!(ids.0, vec![1, 2, 3]);
+        assert!(serde_json::from_value::<StrategyIds>(json!({"a": 1})).is_err());
+        assert!(serde_json::from_value::<StrategyIds>(json!([1, "x"])).is_err());
+    }
+}
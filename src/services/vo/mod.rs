@@ -51,6 +51,9 @@ pub struct RespVO<T> {
     pub msg: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub data: Option<T>,
+    /// graphql 错误(message/locations/path/extensions.code), 仅 graphql 接口返回
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub errors: Option<Vec<serde_json::Value>>,
 }
 
 impl<T> RespVO<T> {
@@ -62,6 +65,16 @@ impl<T> RespVO<T> {
             code: CODE_SUC,
             msg: "".to_string(),
             data: Some(arg.clone()),
+            errors: None,
         }
     }
+
+    ///
+    /// 附带错误列表, code/msg 取第一个错误
+    pub fn with_errors(mut self, code: i32, msg: &str, errors: Vec<serde_json::Value>) -> Self {
+        self.code = code;
+        self.msg = msg.to_owned();
+        self.errors = Some(errors);
+        self
+    }
 }
@@ -1,6 +1,7 @@
 extern crate chrono;
 
 use chrono::prelude::*;
+use serde::Serialize;
 
 ///毫秒时间戳
 pub fn cur_timestamp() -> i64 {
@@ -63,3 +64,211 @@ pub fn value_to_vec_string(
         Err("Value is not an array".into())
     }
 }
+
+///lowerCamelCase/UpperCamelCase 转 snake_case
+pub fn camel_to_snake(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+///snake_case 转 lowerCamelCase/UpperCamelCase
+pub fn snake_to_camel(name: &str, upper_first: bool) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut upper = upper_first;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.push(c.to_ascii_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+///json结构化差异的一项变更, path 为 json pointer
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonChange {
+    pub path: String,
+    // add | remove | replace
+    pub op: &'static str,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+///两个json值的结构化差异(对象按key、数组按下标逐项比较)
+pub fn json_diff(before: &serde_json::Value, after: &serde_json::Value) -> Vec<JsonChange> {
+    let mut changes = vec![];
+    diff_at("", before, after, &mut changes);
+    changes
+}
+
+fn diff_at(
+    path: &str,
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+    changes: &mut Vec<JsonChange>,
+) {
+    use serde_json::Value;
+    let escape = |k: &str| k.replace('~', "~0").replace('/', "~1");
+    match (before, after) {
+        (Value::Object(b), Value::Object(a)) => {
+            for (k, bv) in b.iter() {
+                let p = format!("{}/{}", path, escape(k));
+                match a.get(k) {
+                    Some(av) => diff_at(&p, bv, av, changes),
+                    None => changes.push(JsonChange {
+                        path: p,
+                        op: "remove",
+                        before: Some(bv.clone()),
+                        after: None,
+                    }),
+                }
+            }
+            for (k, av) in a.iter().filter(|(k, _)| !b.contains_key(*k)) {
+                changes.push(JsonChange {
+                    path: format!("{}/{}", path, escape(k)),
+                    op: "add",
+                    before: None,
+                    after: Some(av.clone()),
+                });
+            }
+        }
+        (Value::Array(b), Value::Array(a)) => {
+            for i in 0..b.len().max(a.len()) {
+                let p = format!("{}/{}", path, i);
+                match (b.get(i), a.get(i)) {
+                    (Some(bv), Some(av)) => diff_at(&p, bv, av, changes),
+                    (Some(bv), None) => changes.push(JsonChange {
+                        path: p,
+                        op: "remove",
+                        before: Some(bv.clone()),
+                        after: None,
+                    }),
+                    (None, Some(av)) => changes.push(JsonChange {
+                        path: p,
+                        op: "add",
+                        before: None,
+                        after: Some(av.clone()),
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        (b, a) if b != a => changes.push(JsonChange {
+            path: path.to_owned(),
+            op: "replace",
+            before: Some(b.clone()),
+            after: Some(a.clone()),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // 对象 key 的遍历顺序取决于 serde_json 特性, 按 path 排序后比较
+    fn diff(before: serde_json::Value, after: serde_json::Value) -> Vec<(String, &'static str)> {
+        let mut changes: Vec<(String, &'static str)> = json_diff(&before, &after)
+            .into_iter()
+            .map(|c| (c.path, c.op))
+            .collect();
+        changes.sort();
+        changes
+    }
+
+    #[test]
+    fn equal_values_have_no_changes() {
+        let v = json!({"a": [1, {"b": null}], "c": "x"});
+        assert!(json_diff(&v, &v).is_empty());
+    }
+
+    #[test]
+    fn root_scalar_is_replaced_at_empty_path() {
+        let changes = json_diff(&json!(1), &json!("1"));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, "");
+        assert_eq!(changes[0].op, "replace");
+        assert_eq!(changes[0].before, Some(json!(1)));
+        assert_eq!(changes[0].after, Some(json!("1")));
+    }
+
+    #[test]
+    fn nested_object_add_remove_replace() {
+        assert_eq!(
+            diff(
+                json!({"a": {"x": 1, "y": 2}, "gone": true}),
+                json!({"a": {"x": 1, "y": 3, "z": 4}, "new": null})
+            ),
+            vec![
+                ("/a/y".to_owned(), "replace"),
+                ("/a/z".to_owned(), "add"),
+                ("/gone".to_owned(), "remove"),
+                ("/new".to_owned(), "add"),
+            ]
+        );
+    }
+
+    #[test]
+    fn null_differs_from_missing() {
+        assert_eq!(
+            diff(json!({"a": null}), json!({"a": 0})),
+            vec![("/a".to_owned(), "replace")]
+        );
+        assert_eq!(
+            diff(json!({"a": null}), json!({})),
+            vec![("/a".to_owned(), "remove")]
+        );
+    }
+
+    #[test]
+    fn arrays_compare_by_index() {
+        assert_eq!(
+            diff(json!([1, 2, 3]), json!([1, 4])),
+            vec![("/1".to_owned(), "replace"), ("/2".to_owned(), "remove")]
+        );
+        assert_eq!(
+            diff(json!([]), json!([[0]])),
+            vec![("/0".to_owned(), "add")]
+        );
+    }
+
+    #[test]
+    fn type_change_replaces_whole_value() {
+        assert_eq!(
+            diff(json!({"a": {"b": 1}}), json!({"a": [1]})),
+            vec![("/a".to_owned(), "replace")]
+        );
+    }
+
+    #[test]
+    fn keys_are_escaped_as_json_pointer() {
+        assert_eq!(
+            diff(
+                json!({"a/b": 1, "c~d": 1, "": 1}),
+                json!({"a/b": 2, "c~d": 2, "": 2})
+            ),
+            vec![
+                ("/".to_owned(), "replace"),
+                ("/a~1b".to_owned(), "replace"),
+                ("/c~0d".to_owned(), "replace"),
+            ]
+        );
+    }
+}
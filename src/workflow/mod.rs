@@ -0,0 +1,360 @@
+use entity_graphql::sea_orm_active_enums::Estwftype;
+use sea_orm::ActiveEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::{DError, LogicErr};
+pub mod service;
+
+/// 状态流转方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    Advance,
+    Rollback,
+}
+
+/// 参与状态机校验的表
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkflowTarget {
+    // st_workflow
+    Workflow,
+    // solution_workflow, 由 st_workflow.id_wf_data 关联
+    SolutionWorkflow,
+    // st_wf_sol_pack, 经 st_wf_solution.id_st_wf 关联 st_workflow
+    SolPack,
+}
+
+impl WorkflowTarget {
+    pub const ALL: [WorkflowTarget; 3] = [
+        WorkflowTarget::Workflow,
+        WorkflowTarget::SolutionWorkflow,
+        WorkflowTarget::SolPack,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.table() == name)
+    }
+
+    pub fn table(self) -> &'static str {
+        match self {
+            WorkflowTarget::Workflow => "st_workflow",
+            WorkflowTarget::SolutionWorkflow => "solution_workflow",
+            WorkflowTarget::SolPack => "st_wf_sol_pack",
+        }
+    }
+}
+
+/// 单向流转
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Transition {
+    pub from: String,
+    pub to: String,
+    pub direction: Direction,
+}
+
+/// 合法状态及流转, 同一 from 有多个同向目标时第一个为默认目标
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct StateMachine {
+    pub states: Vec<String>,
+    pub transitions: Vec<Transition>,
+    // solution_workflow: 该状态下未指定目标时按 list_layer 逐层推进/回退(cur_layer)
+    pub layer_state: Option<String>,
+    // st_wf_sol_pack: 进入时写 start_time 并清空 end_time
+    pub start_states: Vec<String>,
+    // st_wf_sol_pack: 进入时写 end_time
+    pub end_states: Vec<String>,
+}
+
+/// 各表、各流程类型(`Estwftype`)的状态机: 内置于 `src/workflow/builtin.json`,
+/// `GQL_WORKFLOW_FILE` 中出现的 `表 -> 流程类型` 整体覆盖内置定义
+///
+/// 状态列是客户端直接写入的自由字符串, 内置状态与库中已有数据不一致时需以文件覆盖;
+/// 库中实际存储的状态可用如下 SQL 列出(启动时也会检查并告警未声明的状态):
+///
+/// ```sql
+/// SELECT st_wf_type, state, count(*) FROM st_workflow GROUP BY 1, 2;
+/// SELECT w.st_wf_type, s.state, count(*) FROM solution_workflow s
+///     JOIN st_workflow w ON w.id_wf_data = s.id GROUP BY 1, 2;
+/// SELECT w.st_wf_type, p.state, count(*) FROM st_wf_sol_pack p
+///     JOIN st_wf_solution s ON s.id = p.id_wf_solution
+///     JOIN st_workflow w ON w.id = s.id_st_wf GROUP BY 1, 2;
+/// ```
+///
+/// 文件按 `表名 -> 流程类型(库中的枚举值) -> 状态机` 组织:
+///
+/// ```json
+/// {
+///     "st_workflow": {
+///         "SOLUTION": {
+///             "states": ["a", "b"],
+///             "transitions": [
+///                 { "from": "a", "to": "b", "direction": "Advance" },
+///                 { "from": "b", "to": "a", "direction": "Rollback" }
+///             ]
+///         }
+///     }
+/// }
+/// ```
+///
+/// 未定义的表/流程类型不允许通过 advanceWorkflow/rollbackWorkflow 流转
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct WorkflowMachines(HashMap<String, HashMap<String, StateMachine>>);
+
+/// 内置状态机, 格式同 `GQL_WORKFLOW_FILE`
+const BUILTIN_MACHINES: &str = include_str!("builtin.json");
+
+impl WorkflowMachines {
+    ///
+    /// 内置状态机, 格式错误在测试中发现
+    pub fn builtin() -> Self {
+        serde_json::from_str(BUILTIN_MACHINES).expect("invalid builtin workflow machines")
+    }
+
+    ///
+    /// 读取并校验状态机文件, 与内置状态机合并
+    pub fn from_file(f: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let js_cont: String = std::fs::read_to_string(f)?;
+        let overrides: WorkflowMachines = serde_json::from_str(js_cont.as_str())?;
+        let errs = overrides.check();
+        if !errs.is_empty() {
+            return Err(errs.join("; ").into());
+        }
+        let mut machines = Self::builtin();
+        machines.merge(overrides);
+        Ok(machines)
+    }
+
+    ///
+    /// 以 `overrides` 中的 `表 -> 流程类型` 替换已有定义
+    fn merge(&mut self, overrides: WorkflowMachines) {
+        for (table, machines) in overrides.0 {
+            self.0.entry(table).or_default().extend(machines);
+        }
+    }
+
+    ///
+    /// 表名需为 [`WorkflowTarget`], 流转及特殊状态需引用已声明的状态
+    fn check(&self) -> Vec<String> {
+        let mut errs = vec![];
+        for (table, machines) in self.0.iter() {
+            if WorkflowTarget::from_name(table).is_none() {
+                errs.push(format!("unknown table <{}>", table));
+            }
+            for (wf_type, m) in machines.iter() {
+                let declared = |s: &String| m.states.contains(s);
+                let undeclared = m
+                    .transitions
+                    .iter()
+                    .flat_map(|t| [&t.from, &t.to])
+                    .chain(m.layer_state.iter())
+                    .chain(m.start_states.iter())
+                    .chain(m.end_states.iter())
+                    .filter(|s| !declared(*s));
+                for s in undeclared {
+                    errs.push(format!("{}.{}: undeclared state <{}>", table, wf_type, s));
+                }
+            }
+        }
+        errs
+    }
+
+    ///
+    /// 表及流程类型对应的状态机
+    pub fn machine(
+        &self,
+        target: WorkflowTarget,
+        wf_type: &Estwftype,
+    ) -> Result<&StateMachine, DError> {
+        let wf_type = wf_type.to_value();
+        self.0
+            .get(target.table())
+            .and_then(|m| m.get(&wf_type))
+            .ok_or_else(|| {
+                illegal(format!(
+                    "no state machine defined for {} <{}>",
+                    target.table(),
+                    wf_type
+                ))
+            })
+    }
+
+    ///
+    /// 表的全部状态机, 用于检查库中已有的状态
+    pub fn machines(
+        &self,
+        target: WorkflowTarget,
+    ) -> impl Iterator<Item = (&String, &StateMachine)> {
+        self.0.get(target.table()).into_iter().flatten()
+    }
+}
+
+impl StateMachine {
+    ///
+    /// 校验并确定目标状态: 未指定 `to` 时取该方向的默认目标
+    pub fn resolve<'a>(
+        &'a self,
+        from: &str,
+        to: Option<&str>,
+        direction: Direction,
+    ) -> Result<&'a str, DError> {
+        if !self.states.iter().any(|s| s == from) {
+            return Err(illegal(format!("unknown state <{}>", from)));
+        }
+        let mut candidates = self
+            .transitions
+            .iter()
+            .filter(|t| t.from == from && t.direction == direction)
+            .map(|t| t.to.as_str());
+        let target = match to {
+            Some(to) => candidates.find(|t| *t == to),
+            None => candidates.next(),
+        };
+        target.ok_or_else(|| {
+            illegal(format!(
+                "{:?} from <{}> to <{}> is not allowed",
+                direction,
+                from,
+                to.unwrap_or("*")
+            ))
+        })
+    }
+
+    pub fn is_layer_state(&self, state: &str) -> bool {
+        self.layer_state.as_deref() == Some(state)
+    }
+}
+
+pub fn illegal(msg: String) -> DError {
+    DError::Custom(LogicErr::IllegalTransition(msg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn machine() -> StateMachine {
+        serde_json::from_value(json!({
+            "states": ["a", "b", "c", "x"],
+            "transitions": [
+                { "from": "a", "to": "b", "direction": "Advance" },
+                { "from": "a", "to": "x", "direction": "Advance" },
+                { "from": "b", "to": "c", "direction": "Advance" },
+                { "from": "b", "to": "a", "direction": "Rollback" }
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn err_code(r: Result<&str, DError>) -> i32 {
+        r.unwrap_err().err_code()
+    }
+
+    #[test]
+    fn resolve_default_and_explicit_target() {
+        let m = machine();
+        assert_eq!(m.resolve("a", None, Direction::Advance).unwrap(), "b");
+        assert_eq!(m.resolve("a", Some("x"), Direction::Advance).unwrap(), "x");
+        assert_eq!(m.resolve("b", None, Direction::Rollback).unwrap(), "a");
+    }
+
+    #[test]
+    fn resolve_rejects_illegal_transitions() {
+        let m = machine();
+        let code = LogicErr::IllegalTransition(String::new()).code();
+        // 方向不符
+        assert_eq!(
+            err_code(m.resolve("a", Some("b"), Direction::Rollback)),
+            code
+        );
+        // 跳级
+        assert_eq!(
+            err_code(m.resolve("a", Some("c"), Direction::Advance)),
+            code
+        );
+        // 终态无后继
+        assert_eq!(err_code(m.resolve("c", None, Direction::Advance)), code);
+        assert_eq!(err_code(m.resolve("x", None, Direction::Rollback)), code);
+        // 未声明的状态
+        assert_eq!(err_code(m.resolve("zz", None, Direction::Advance)), code);
+        assert_eq!(
+            err_code(m.resolve("a", Some("zz"), Direction::Advance)),
+            code
+        );
+    }
+
+    #[test]
+    fn machines_check_undeclared_states() {
+        let machines: WorkflowMachines = serde_json::from_value(json!({
+            "st_workflow": { "SOLUTION": {
+                "states": ["a"],
+                "transitions": [{ "from": "a", "to": "b", "direction": "Advance" }],
+                "layerState": "c"
+            } },
+            "st_unknown": {}
+        }))
+        .unwrap();
+        let mut errs = machines.check();
+        errs.sort();
+        assert_eq!(
+            errs,
+            vec![
+                "st_workflow.SOLUTION: undeclared state <b>",
+                "st_workflow.SOLUTION: undeclared state <c>",
+                "unknown table <st_unknown>",
+            ]
+        );
+    }
+
+    #[test]
+    fn builtin_machines_cover_every_table() {
+        let machines = WorkflowMachines::builtin();
+        assert!(machines.check().is_empty());
+        for target in WorkflowTarget::ALL {
+            assert!(machines.machine(target, &Estwftype::Solution).is_ok());
+        }
+        let sw = machines
+            .machine(WorkflowTarget::SolutionWorkflow, &Estwftype::Solution)
+            .unwrap();
+        assert!(sw.is_layer_state("layer_rollout"));
+        assert_eq!(
+            sw.resolve("draft", None, Direction::Advance).unwrap(),
+            "testing"
+        );
+    }
+
+    #[test]
+    fn file_overrides_builtin_per_type() {
+        let mut machines = WorkflowMachines::builtin();
+        machines.merge(
+            serde_json::from_value(json!({ "st_workflow": { "SOLUTION": { "states": ["a"] } } }))
+                .unwrap(),
+        );
+        let m = machines
+            .machine(WorkflowTarget::Workflow, &Estwftype::Solution)
+            .unwrap();
+        assert_eq!(m.states, vec!["a"]);
+        // 未覆盖的表保留内置定义
+        assert!(machines
+            .machine(WorkflowTarget::SolPack, &Estwftype::Solution)
+            .unwrap()
+            .states
+            .contains(&"packing".to_owned()));
+    }
+
+    #[test]
+    fn machine_lookup_by_table_and_type() {
+        let machines: WorkflowMachines =
+            serde_json::from_value(json!({ "st_workflow": { "SOLUTION": { "states": ["a"] } } }))
+                .unwrap();
+        assert!(machines
+            .machine(WorkflowTarget::Workflow, &Estwftype::Solution)
+            .is_ok());
+        assert!(machines
+            .machine(WorkflowTarget::SolPack, &Estwftype::Solution)
+            .is_err());
+    }
+}
@@ -0,0 +1,306 @@
+use chrono::{DateTime, FixedOffset, Local};
+use entity_graphql::sea_orm_active_enums::Estwftype;
+use entity_graphql::{solution_workflow, st_wf_sol_pack, st_wf_solution, st_workflow};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DatabaseTransaction, DbBackend,
+    EntityTrait, FromQueryResult, QueryFilter, QuerySelect, Set, Statement, TransactionTrait,
+};
+use serde::Serialize;
+
+use super::{illegal, Direction, WorkflowMachines, WorkflowTarget};
+use crate::error::{DError, LogicErr};
+
+/// 库中各表按流程类型实际存储的状态
+const STORED_STATES_SQL: [(WorkflowTarget, &str); 3] = [
+    (
+        WorkflowTarget::Workflow,
+        "SELECT st_wf_type::text AS wf_type, state, count(*) AS cnt FROM st_workflow GROUP BY 1, 2",
+    ),
+    (
+        WorkflowTarget::SolutionWorkflow,
+        r#"SELECT w.st_wf_type::text AS wf_type, s.state, count(*) AS cnt
+FROM solution_workflow s JOIN st_workflow w ON w.id_wf_data = s.id
+GROUP BY 1, 2"#,
+    ),
+    (
+        WorkflowTarget::SolPack,
+        r#"SELECT w.st_wf_type::text AS wf_type, p.state, count(*) AS cnt
+FROM st_wf_sol_pack p
+JOIN st_wf_solution s ON s.id = p.id_wf_solution
+JOIN st_workflow w ON w.id = s.id_st_wf
+GROUP BY 1, 2"#,
+    ),
+];
+
+#[derive(Debug, FromQueryResult)]
+struct StoredState {
+    wf_type: String,
+    state: String,
+    cnt: i64,
+}
+
+/// 一次状态流转的结果
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransitionResult {
+    pub entity: &'static str,
+    pub id: i64,
+    pub direction: Direction,
+    pub from_state: String,
+    pub to_state: String,
+    // 仅 solution_workflow
+    pub cur_layer: Option<i32>,
+    pub cur_layer_step: Option<i32>,
+}
+
+///
+/// 在事务中锁定记录并执行状态流转, 所属 st_workflow 处于 lock 状态时拒绝
+pub async fn transition(
+    db: &DatabaseConnection,
+    machines: &WorkflowMachines,
+    target: WorkflowTarget,
+    id: i64,
+    to: Option<&str>,
+    direction: Direction,
+) -> Result<TransitionResult, DError> {
+    let txn = db.begin().await?;
+    let result = match target {
+        WorkflowTarget::Workflow => transition_workflow(&txn, machines, id, to, direction).await?,
+        WorkflowTarget::SolutionWorkflow => {
+            transition_solution_workflow(&txn, machines, id, to, direction).await?
+        }
+        WorkflowTarget::SolPack => transition_sol_pack(&txn, machines, id, to, direction).await?,
+    };
+    txn.commit().await?;
+    tracing::info!(
+        "[workflow] {} <{}> {:?}: {} -> {}",
+        result.entity,
+        result.id,
+        direction,
+        result.from_state,
+        result.to_state
+    );
+    Ok(result)
+}
+
+async fn transition_workflow(
+    txn: &DatabaseTransaction,
+    machines: &WorkflowMachines,
+    id: i64,
+    to: Option<&str>,
+    direction: Direction,
+) -> Result<TransitionResult, DError> {
+    let target = WorkflowTarget::Workflow;
+    let wf = st_workflow::Entity::find_by_id(to_i32(target, id)?)
+        .filter(not_deleted())
+        .lock_exclusive()
+        .one(txn)
+        .await?
+        .ok_or_else(|| not_found(target, id))?;
+    ensure_unlocked(&wf)?;
+    let next = machines
+        .machine(target, &wf.st_wf_type)?
+        .resolve(&wf.state, to, direction)?;
+    let from_state = wf.state.clone();
+    let mut am: st_workflow::ActiveModel = wf.into();
+    am.state = Set(next.to_owned());
+    am.editer_time = Set(Some(now()));
+    am.update(txn).await?;
+    Ok(TransitionResult {
+        entity: target.table(),
+        id,
+        direction,
+        from_state,
+        to_state: next.to_owned(),
+        cur_layer: None,
+        cur_layer_step: None,
+    })
+}
+
+///
+/// 处于状态机的 `layerState` 时, 未指定目标状态则按 `list_layer` 逐层推进/回退,
+/// 到达首末层后再流转状态
+async fn transition_solution_workflow(
+    txn: &DatabaseTransaction,
+    machines: &WorkflowMachines,
+    id: i64,
+    to: Option<&str>,
+    direction: Direction,
+) -> Result<TransitionResult, DError> {
+    let target = WorkflowTarget::SolutionWorkflow;
+    let sw = solution_workflow::Entity::find_by_id(to_i32(target, id)?)
+        .lock_exclusive()
+        .one(txn)
+        .await?
+        .ok_or_else(|| not_found(target, id))?;
+    let owner = st_workflow::Entity::find()
+        .filter(st_workflow::Column::IdWfData.eq(sw.id))
+        .filter(st_workflow::Column::StWfType.eq(Estwftype::Solution))
+        .filter(not_deleted())
+        .lock_shared()
+        .one(txn)
+        .await?;
+    if let Some(owner) = &owner {
+        ensure_unlocked(owner)?;
+    }
+    let wf_type = owner.map_or(Estwftype::Solution, |o| o.st_wf_type);
+    let machine = machines.machine(target, &wf_type)?;
+    let layers = sw.list_layer.as_array().map_or(0, |a| a.len()) as i32;
+    let in_layers = machine.is_layer_state(&sw.state);
+    let (next, layer) = match (to, direction) {
+        (None, Direction::Advance) if in_layers && sw.cur_layer + 1 < layers => {
+            (sw.state.as_str(), sw.cur_layer + 1)
+        }
+        (None, Direction::Rollback) if in_layers && sw.cur_layer > 0 => {
+            (sw.state.as_str(), sw.cur_layer - 1)
+        }
+        _ => {
+            let next = machine.resolve(&sw.state, to, direction)?;
+            // 推进进入分层状态从首层开始, 回退进入则从末层开始
+            let layer = match (machine.is_layer_state(next), direction) {
+                (true, Direction::Advance) => 0,
+                (true, Direction::Rollback) => (layers - 1).max(0),
+                (false, _) => sw.cur_layer,
+            };
+            (next, layer)
+        }
+    };
+    let next = next.to_owned();
+    let from_state = sw.state.clone();
+    let step = if next == sw.state && layer == sw.cur_layer {
+        sw.cur_layer_step
+    } else {
+        0
+    };
+    let mut am: solution_workflow::ActiveModel = sw.into();
+    am.state = Set(next.clone());
+    am.cur_layer = Set(layer);
+    am.cur_layer_step = Set(step);
+    am.editer_time = Set(Some(now()));
+    am.update(txn).await?;
+    Ok(TransitionResult {
+        entity: target.table(),
+        id,
+        direction,
+        from_state,
+        to_state: next,
+        cur_layer: Some(layer),
+        cur_layer_step: Some(step),
+    })
+}
+
+async fn transition_sol_pack(
+    txn: &DatabaseTransaction,
+    machines: &WorkflowMachines,
+    id: i64,
+    to: Option<&str>,
+    direction: Direction,
+) -> Result<TransitionResult, DError> {
+    let target = WorkflowTarget::SolPack;
+    let pack = st_wf_sol_pack::Entity::find_by_id(to_i32(target, id)?)
+        .filter(st_wf_sol_pack::Column::IsDelete.eq(false))
+        .lock_exclusive()
+        .one(txn)
+        .await?
+        .ok_or_else(|| not_found(target, id))?;
+    let solution = st_wf_solution::Entity::find_by_id(i64::from(pack.id_wf_solution))
+        .one(txn)
+        .await?;
+    let owner = match solution.and_then(|s| i32::try_from(s.id_st_wf).ok()) {
+        Some(wf_id) => {
+            st_workflow::Entity::find_by_id(wf_id)
+                .lock_shared()
+                .one(txn)
+                .await?
+        }
+        None => None,
+    };
+    if let Some(owner) = &owner {
+        ensure_unlocked(owner)?;
+    }
+    let wf_type = owner.map_or(Estwftype::Solution, |o| o.st_wf_type);
+    let machine = machines.machine(target, &wf_type)?;
+    let next = machine.resolve(&pack.state, to, direction)?;
+    let from_state = pack.state.clone();
+    let mut am: st_wf_sol_pack::ActiveModel = pack.into();
+    am.state = Set(next.to_owned());
+    if machine.start_states.iter().any(|s| s == next) {
+        am.start_time = Set(Some(now()));
+        am.end_time = Set(None);
+    } else if machine.end_states.iter().any(|s| s == next) {
+        am.end_time = Set(Some(now()));
+    }
+    am.update(txn).await?;
+    Ok(TransitionResult {
+        entity: target.table(),
+        id,
+        direction,
+        from_state,
+        to_state: next.to_owned(),
+        cur_layer: None,
+        cur_layer_step: None,
+    })
+}
+
+///
+/// 检查库中已有的状态是否都已在状态机中声明; 未声明状态的记录无法再流转, 只告警不阻止启动
+pub async fn check_stored_states(
+    db: &DatabaseConnection,
+    machines: &WorkflowMachines,
+) -> Result<(), DError> {
+    for (target, sql) in STORED_STATES_SQL {
+        if machines.machines(target).next().is_none() {
+            continue;
+        }
+        let rows = StoredState::find_by_statement(Statement::from_string(DbBackend::Postgres, sql))
+            .all(db)
+            .await?;
+        for row in rows {
+            let machine = machines
+                .machines(target)
+                .find(|(wf_type, _)| **wf_type == row.wf_type);
+            match machine {
+                Some((_, m)) if !m.states.contains(&row.state) => tracing::warn!(
+                    "[workflow] {} <{}>: {} rows in undeclared state <{}>",
+                    target.table(),
+                    row.wf_type,
+                    row.cnt,
+                    row.state
+                ),
+                Some(_) => {}
+                None => tracing::warn!(
+                    "[workflow] {} <{}>: {} rows without state machine",
+                    target.table(),
+                    row.wf_type,
+                    row.cnt
+                ),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn ensure_unlocked(wf: &st_workflow::Model) -> Result<(), DError> {
+    if wf.lock == Some(true) {
+        return Err(illegal(format!("st_workflow <{}> is locked", wf.id)));
+    }
+    Ok(())
+}
+
+fn not_deleted() -> Condition {
+    Condition::any()
+        .add(st_workflow::Column::IsDelete.is_null())
+        .add(st_workflow::Column::IsDelete.eq(false))
+}
+
+fn to_i32(target: WorkflowTarget, id: i64) -> Result<i32, DError> {
+    i32::try_from(id).map_err(|_| not_found(target, id))
+}
+
+fn not_found(target: WorkflowTarget, id: i64) -> DError {
+    DError::Custom(LogicErr::NotFound(format!("{} <{}>", target.table(), id)))
+}
+
+fn now() -> DateTime<FixedOffset> {
+    Local::now().fixed_offset()
+}